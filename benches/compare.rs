@@ -0,0 +1,115 @@
+//! Times finding the changed pixels of a big set of frames, the old per pixel way against the row slices
+//! in [`autocrop::compare`], plus a whole crop for reference.
+//!
+//! Run with `cargo bench --bench compare`, optionally followed by `-- <FRAMES> <WIDTH> <HEIGHT>`
+//! [default: 24 1920 1080]. Frames are RGB like most exported pngs, so the old way has to convert every pixel.
+
+use std::time::{Duration, Instant};
+
+use autocrop::compare;
+use autocrop::metric::metric;
+use autocrop::{AlphaMode, Autocrop, MetricType, Pos2, RectangleRange, Shift};
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+
+fn main() {
+    // cargo passes --bench along, skip anything that isn't a number
+    let args: Vec<u32> = std::env::args()
+        .skip(1)
+        .filter_map(|arg| arg.parse().ok())
+        .collect();
+    let frames = *args.first().unwrap_or(&24);
+    let width = *args.get(1).unwrap_or(&1920);
+    let height = *args.get(2).unwrap_or(&1080);
+
+    println!("making {} {}x{} frames", frames, width, height);
+    let images = make_frames(frames, width, height);
+
+    let rgb = metric(MetricType::Rgb, [0; 4]);
+    let threshold = rgb.threshold(0.0);
+    let changed = |bg_px, px| rgb.difference(bg_px, px, AlphaMode::Straight) > threshold;
+
+    let per_pixel = time("per pixel get_pixel, two passes", || {
+        let bg = &images[0];
+        let mut range = RectangleRange::new();
+        for (x, y, bg_px) in bg.pixels() {
+            for image in &images[1..] {
+                if changed(bg_px, image.get_pixel(x, y)) && range.correct(x, y) {
+                    break;
+                }
+            }
+        }
+        let mut pixels = vec![vec![]; images.len()];
+        for (x, y, bg_px) in bg.pixels() {
+            if range.contains(x, y) {
+                for (i, image) in images.iter().enumerate().skip(1) {
+                    if changed(bg_px, image.get_pixel(x, y)) {
+                        pixels[i].push(Pos2::new(x, y));
+                    }
+                }
+            }
+        }
+        pixels.iter().map(Vec::len).sum()
+    });
+
+    let row_slices = time("row slices, one pass", || {
+        let bg = compare::as_rgba8(&images[0]);
+        images[1..]
+            .iter()
+            .map(|image| {
+                let image = compare::as_rgba8(image);
+                compare::differences(&bg, &image, Shift::default(), changed, true)
+                    .pixels
+                    .len()
+            })
+            .sum()
+    });
+
+    println!("row slices are {:.1}x faster", ratio(per_pixel, row_slices));
+
+    time("whole crop, every core", || {
+        let result = Autocrop::new().crop(&images).unwrap();
+        result.outputs.len()
+    });
+}
+
+/// A noisy textured background with a sprite moving across it, so every frame has something to find.
+fn make_frames(frames: u32, width: u32, height: u32) -> Vec<DynamicImage> {
+    let background = RgbImage::from_fn(width, height, |x, y| {
+        Rgb([
+            (x % 251) as u8,
+            (y % 241) as u8,
+            ((x * 7 + y * 13) % 256) as u8,
+        ])
+    });
+    (0..frames)
+        .map(|frame| {
+            let mut image = background.clone();
+            let size = (width.min(height) / 8).max(1);
+            let left = (frame * width / frames.max(1)).min(width - size);
+            for y in height / 2 - size / 2..height / 2 + size / 2 {
+                for x in left..left + size {
+                    image.put_pixel(x, y, Rgb([255, (frame * 10) as u8, 0]));
+                }
+            }
+            DynamicImage::ImageRgb8(image)
+        })
+        .collect()
+}
+
+/// Runs `f` once, printing how long it took and what it returned.
+fn time(name: &str, f: impl FnOnce() -> usize) -> Duration {
+    let start = Instant::now();
+    let found = f();
+    let elapsed = start.elapsed();
+    println!(
+        "{:<34} {:>8.1} ms  ({} found)",
+        name,
+        elapsed.as_secs_f64() * 1000.0,
+        found
+    );
+    elapsed
+}
+
+fn ratio(slow: Duration, fast: Duration) -> f64 {
+    slow.as_secs_f64() / fast.as_secs_f64().max(f64::EPSILON)
+}
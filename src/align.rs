@@ -0,0 +1,102 @@
+//! Lining images up with the background before comparing, for sprites nudged a few pixels between exports.
+
+use image::{GenericImageView, Rgba};
+
+use crate::crop::{Pos2, Shift};
+
+/// About how many pixels are compared for each shift tried, spread evenly over the image.
+const SAMPLES: u32 = 16384;
+
+/// Finds the shift, at most `radius` pixels each way, that leaves the fewest pixels of `image` changed
+/// against `background`. Pixels the shifted background doesn't reach count as changed,
+/// so ties go to the smaller shift.
+///
+/// Only a spread out sample of the pixels is compared, so this stays quick for big images and windows.
+pub fn find_shift<I: GenericImageView<Pixel = Rgba<u8>>>(
+    background: &I,
+    image: &I,
+    radius: u32,
+    changed: &dyn Fn(Rgba<u8>, Rgba<u8>) -> bool,
+) -> Shift {
+    let (width, height) = image.dimensions();
+    let step = ((width as f64 * height as f64 / SAMPLES as f64).sqrt() as u32).max(1);
+    let radius = radius as i32;
+
+    // closest shifts first, so a later one has to be strictly better to win
+    let mut shifts = vec![];
+    for y in -radius..=radius {
+        for x in -radius..=radius {
+            shifts.push(Shift::new(x, y));
+        }
+    }
+    shifts.sort_by_key(|shift| shift.x.abs().max(shift.y.abs()));
+
+    let mut best = Shift::default();
+    let mut best_score = usize::MAX;
+    for shift in shifts {
+        let mut score = 0;
+        'rows: for y in (0..height).step_by(step as usize) {
+            for x in (0..width).step_by(step as usize) {
+                let different = match shifted_pixel(background, shift, x, y) {
+                    Some(bg_px) => changed(bg_px, image.get_pixel(x, y)),
+                    None => true,
+                };
+                if different {
+                    score += 1;
+                    // already worse, no need to finish
+                    if score >= best_score {
+                        break 'rows;
+                    }
+                }
+            }
+        }
+
+        if score < best_score {
+            best = shift;
+            best_score = score;
+        }
+    }
+
+    best
+}
+
+/// The background pixel that lines up with (x, y) of an image moved by `shift`,
+/// `None` if the background doesn't reach there.
+pub fn shifted_pixel<I: GenericImageView<Pixel = Rgba<u8>>>(
+    background: &I,
+    shift: Shift,
+    x: u32,
+    y: u32,
+) -> Option<Rgba<u8>> {
+    let bg_x = x as i64 - shift.x as i64;
+    let bg_y = y as i64 - shift.y as i64;
+    if bg_x < 0
+        || bg_y < 0
+        || bg_x >= background.width() as i64
+        || bg_y >= background.height() as i64
+    {
+        None
+    } else {
+        Some(background.get_pixel(bg_x as u32, bg_y as u32))
+    }
+}
+
+/// Every pixel of a `width` x `height` image moved by `shift` that a `bg_size` background doesn't reach,
+/// see [`shifted_pixel`]. Sorted top to bottom then left to right.
+pub fn uncovered(width: u32, height: u32, bg_size: (u32, u32), shift: Shift) -> Vec<Pos2> {
+    let reaches = |i: u32, shift: i32, bg_len: u32| {
+        let bg_i = i as i64 - shift as i64;
+        bg_i >= 0 && bg_i < bg_len as i64
+    };
+
+    let mut pixels = vec![];
+    for y in 0..height {
+        let row_reached = reaches(y, shift.y, bg_size.1);
+        for x in 0..width {
+            if !row_reached || !reaches(x, shift.x, bg_size.0) {
+                pixels.push(Pos2::new(x, y));
+            }
+        }
+    }
+    pixels
+}
@@ -1,21 +1,25 @@
 use std::{
-    error::Error,
     path::PathBuf,
     str::FromStr,
-    sync::mpsc::{self, Receiver, Sender},
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    sync::Mutex,
+    thread,
 };
 
 use eframe::{
     egui::{self, Align, NumExt},
     epi,
 };
-use image::DynamicImage;
 use native_dialog::{FileDialog, MessageDialog, MessageType};
 
 use crate::config::Config;
-use crate::texture::TextureManager;
+use crate::crop::Autocrop;
+use crate::error::CropError;
+use crate::progress::{CancelToken, Phase, Progress, Update};
+use crate::texture::{Loaded, TextureManager};
 // auto crop user interface
 use crate::ui as acui;
+use crate::ui::failed::FailedAction;
 
 /// crate version, for the display on the bottom right
 pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
@@ -35,8 +39,25 @@ pub const SCROLLBAR_ADJUST: f32 = 35.0;
 #[derive(Default)]
 pub struct AutocropApp {
     tex_manager: TextureManager,
-    reciever: Option<Receiver<DynamicImage>>,
+    reciever: Option<Receiver<Loaded>>,
     config: Config,
+    /// The crop running in the background, if there is one.
+    crop_job: Option<CropJob>,
+}
+
+/// What the crop thread sends back to the ui.
+enum CropMessage {
+    Progress(Update),
+    /// The crop is over, with the background it picked if that should be shown.
+    Done(Result<Option<usize>, CropError>),
+}
+
+/// A crop running on its own thread, see [`AutocropApp::start_crop`].
+struct CropJob {
+    reciever: Receiver<CropMessage>,
+    cancel: CancelToken,
+    /// The latest progress, `None` until the first update comes in.
+    progress: Option<Update>,
 }
 
 impl AutocropApp {
@@ -44,12 +65,37 @@ impl AutocropApp {
         Self::default()
     }
 
-    /// Convenience function to error into a file dialogue instead of exploding everything
-    fn dialogue_error(e: Box<dyn Error>) {
+    /// Shows a crop or loading error in a dialogue, titled with the file it's about if there is one.
+    fn crop_error_dialog(e: &CropError) {
+        let file_name = e
+            .path()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let title = match e {
+            CropError::Decode { .. } => format!("Could not open {}", file_name),
+            CropError::Io { .. } => format!("Could not access {}", file_name),
+            CropError::SizeMismatch { .. } => "Images are different sizes".to_string(),
+            CropError::IllegalName { .. } => "Illegal file name".to_string(),
+            CropError::Cancelled => "Crop cancelled".to_string(),
+            CropError::Invalid(_) => "Error cropping files".to_string(),
+        };
+        let text = match e {
+            CropError::Cancelled => {
+                "The crop was stopped, outputs already saved are still in the output directory."
+                    .to_string()
+            }
+            e => e.to_string(),
+        };
+        let message_type = match e {
+            CropError::Cancelled | CropError::SizeMismatch { .. } => MessageType::Info,
+            _ => MessageType::Error,
+        };
+
         MessageDialog::new()
-            .set_type(MessageType::Error)
-            .set_title("Error!")
-            .set_text(&format!("An error has occured: {}", e.to_string()))
+            .set_type(message_type)
+            .set_title(&title)
+            .set_text(&text)
             .show_alert()
             .unwrap();
     }
@@ -79,14 +125,161 @@ impl AutocropApp {
         }
     }
 
+    /// Crops the selected images with the current config and writes them to the output directory.
+    /// The images are opened as they're cropped, see [`Autocrop::crop_files_to`].
+    /// Returns the background that was picked, if it should be shown in the previewer.
+    fn crop(
+        paths: &[PathBuf],
+        config: &Config,
+        progress: Progress,
+    ) -> Result<Option<usize>, CropError> {
+        let autocrop = Autocrop::from_config(config).progress(progress.clone());
+
+        // every group goes in its own subfolder, grouping needs every image at once
+        if config.cluster {
+            let mut images = vec![];
+            progress.start(Phase::Opening, paths.len());
+            for (i, path) in paths.iter().enumerate() {
+                progress.check()?;
+                let image = image::open(path).map_err(|e| CropError::decode(path, e))?;
+                images.push(image);
+                progress.step(Phase::Opening, i, paths.len());
+            }
+            let clusters = autocrop.crop_clusters(&images)?;
+            for (n, cluster) in clusters.into_iter().enumerate() {
+                progress.check()?;
+                cluster.with_sources(paths).save(
+                    &config.output_path,
+                    n,
+                    &config.bg_name,
+                    &config.file_name,
+                )?;
+            }
+            return Ok(None);
+        }
+
+        let manifest = autocrop.crop_files_to(
+            paths,
+            &config.output_path,
+            &config.bg_name,
+            &config.file_name,
+        )?;
+
+        // show which background was picked in the previewer, a sequential crop always starts from the first image
+        if manifest.synthetic_background || manifest.sequential {
+            return Ok(None);
+        }
+        Ok(manifest
+            .outputs
+            .iter()
+            .find(|entry| entry.is_background)
+            .map(|entry| entry.index))
+    }
+
+    /// Starts cropping on a seperate thread so the window keeps drawing, the same way textures are loaded.
+    fn start_crop(tex_manager: &TextureManager, config: &Config) -> CropJob {
+        let (tx, rx) = mpsc::channel();
+        let cancel = CancelToken::new();
+        // the callback is called from every thread the crop runs on, so the sender is shared behind a lock
+        let sender = Mutex::new(tx.clone());
+        let progress = Progress::new(move |update| {
+            let _ = sender.lock().unwrap().send(CropMessage::Progress(update));
+        })
+        .cancel_token(cancel.clone());
+
+        let paths = tex_manager.input_paths.clone();
+        let config = config.clone();
+        thread::spawn(move || {
+            let result = AutocropApp::crop(&paths, &config, progress);
+            let _ = tx.send(CropMessage::Done(result));
+        });
+
+        CropJob {
+            reciever: rx,
+            cancel,
+            progress: None,
+        }
+    }
+
+    /// Tells how the crop went once its thread is done, and offers to open the output.
+    fn crop_finished(result: Result<Option<usize>, CropError>, config: &mut Config) {
+        match result {
+            Ok(background) => {
+                if let Some(background) = background {
+                    config.background = background;
+                }
+                let confirm = MessageDialog::new()
+                    .set_type(MessageType::Info)
+                    .set_title("Crop complete!")
+                    .set_text("Open output now?")
+                    .show_confirm()
+                    .unwrap();
+                if confirm {
+                    // i have no gaurantee that literally any of these work, if they don't, oh well.
+                    use std::process::Command;
+                    #[cfg(target_os = "windows")]
+                    let _ = Command::new("explorer")
+                        .arg(config.output_path.as_os_str())
+                        .spawn();
+
+                    #[cfg(target_os = "linux")]
+                    let _ = Command::new("xdg-open")
+                        .arg(config.output_path.as_os_str())
+                        .spawn();
+
+                    #[cfg(target_os = "macos")]
+                    let _ = Command::new("open")
+                        .arg(config.output_path.as_os_str())
+                        .spawn();
+                }
+            }
+            Err(e) => AutocropApp::crop_error_dialog(&e),
+        }
+    }
+
+    /// Asks for a crop's manifest and where to put the rebuilt images, then rebuilds the originals.
+    fn reconstruct(output_path: &PathBuf) -> Result<(), CropError> {
+        let path = AutocropApp::path_or_desktop(output_path);
+        let manifest = match FileDialog::new()
+            .set_location(&path)
+            .add_filter("Autocrop Manifest", &["json"])
+            .show_open_single_file()
+            .unwrap()
+        {
+            Some(manifest) => manifest,
+            None => return Ok(()),
+        };
+
+        let location = manifest.parent().map(PathBuf::from).unwrap_or(path);
+        let output = match FileDialog::new()
+            .set_location(&location)
+            .show_open_single_dir()
+            .unwrap()
+        {
+            Some(output) => output,
+            None => return Ok(()),
+        };
+
+        let written = crate::reconstruct_manifest(&manifest, &output)?;
+
+        MessageDialog::new()
+            .set_type(MessageType::Info)
+            .set_title("Reconstruction complete!")
+            .set_text(&format!("Rebuilt {} images.", written.len()))
+            .show_alert()
+            .unwrap();
+
+        Ok(())
+    }
+
     /// Function that opens and loads images and textures into the program.
     fn open_files(
         alloc: &mut dyn epi::TextureAllocator,
         tex_manager: &mut TextureManager,
-        sender: Sender<DynamicImage>,
-        input_path: &mut PathBuf,
-    ) -> Result<(), Box<dyn Error>> {
-        let path = AutocropApp::path_or_desktop(input_path);
+        sender: Sender<Loaded>,
+        config: &mut Config,
+    ) -> Result<(), CropError> {
+        let path = AutocropApp::path_or_desktop(&config.input_path);
         let paths = FileDialog::new()
             .set_location(&path)
             .add_filter("PNG Image", &["png"])
@@ -104,37 +297,38 @@ impl AutocropApp {
                     .show_alert()
                     .unwrap();
             }
-            2..=10000 => {
-                // check if images are the same resolution, if not return
-                let mut iter = paths.iter();
-                let (width, height) =
-                    image::io::Reader::open(iter.next().unwrap())?.into_dimensions()?;
-                for path in iter {
-                    if (width, height) != image::io::Reader::open(path)?.into_dimensions()? {
-                        MessageDialog::new()
-                            .set_type(MessageType::Info)
-                            .set_title("Alert")
-                            .set_text("Images must be the same resolution.")
-                            .show_alert()
-                            .unwrap();
+            _ => {
+                // check if images are the same resolution, if not return. padding takes any size
+                if !config.pad {
+                    let mut names = vec![];
+                    let mut sizes = vec![];
+                    for path in &paths {
+                        // a file that can't be read shows up with the failed ones once it's loaded
+                        match image::image_dimensions(path) {
+                            Ok(size) => sizes.push(size),
+                            Err(_) => continue,
+                        }
+                        names.push(
+                            path.file_name()
+                                .unwrap_or_else(|| path.as_os_str())
+                                .to_string_lossy()
+                                .into_owned(),
+                        );
+                    }
+                    if let Some(e) = crate::pad::size_error(&names, &sizes) {
+                        AutocropApp::crop_error_dialog(&e);
                         return Ok(());
                     }
                 }
 
                 // actually load the textures
-                *input_path = paths[0].clone();
-                input_path.pop();
+                config.input_path = paths[0].clone();
+                config.input_path.pop();
+                // the old background index means nothing for the new images
+                config.background = 0;
                 tex_manager.input_paths = paths;
                 tex_manager.reload_textures(alloc, sender);
             }
-            _ => {
-                MessageDialog::new()
-                    .set_type(MessageType::Info)
-                    .set_title("10k+ images")
-                    .set_text("You cannot select more than 10000 images.")
-                    .show_alert()
-                    .unwrap();
-            }
         };
 
         Ok(())
@@ -175,8 +369,42 @@ impl epi::App for AutocropApp {
             tex_manager,
             reciever,
             config,
+            crop_job,
         } = self;
 
+        // a running crop gets its progress and a cancel button along the bottom, and everything else is disabled
+        if let Some(job) = crop_job {
+            let mut finished = None;
+            loop {
+                match job.reciever.try_recv() {
+                    Ok(CropMessage::Progress(update)) => job.progress = Some(update),
+                    Ok(CropMessage::Done(result)) => {
+                        finished = Some(result);
+                        break;
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    // the thread went away without saying it's done, so it must have panicked
+                    Err(TryRecvError::Disconnected) => {
+                        finished = Some(Err("The crop stopped unexpectedly.".into()));
+                        break;
+                    }
+                }
+            }
+
+            if let Some(result) = finished {
+                *crop_job = None;
+                AutocropApp::crop_finished(result, config);
+            } else {
+                ctx.request_repaint();
+                egui::TopBottomPanel::bottom("crop_progress").show(ctx, |ui| {
+                    if acui::progress::draw_crop_progress(ui, job.progress.as_ref()).clicked() {
+                        job.cancel.cancel();
+                    }
+                });
+            }
+        }
+        let cropping = crop_job.is_some();
+
         // Most the ui takes place on a scrollable central panel
         // this is mostly for legacy reasons, but, also because it is technically possible
         // for the output directory text to expand the content past the minimum window height
@@ -187,7 +415,10 @@ impl epi::App for AutocropApp {
             egui::ScrollArea::from_max_height(ui.clip_rect().height() - SCROLLBAR_ADJUST)
                 .enable_scrolling(ui.enabled())
                 .show(ui, |ui| {
-                    // Disable all widgets while loading new images in.
+                    // Disable all widgets while cropping or loading new images in.
+                    if cropping {
+                        ui.set_enabled(false);
+                    }
                     if reciever.is_some() {
                         let cont = tex_manager
                             .update_textures(frame.tex_allocator(), reciever.as_mut().unwrap());
@@ -210,6 +441,8 @@ impl epi::App for AutocropApp {
 
                     // leniency slider
                     acui::leniency::draw_leniency_slider(ui, config);
+                    acui::alpha::draw_alpha_selector(ui, config);
+                    acui::mask::draw_mask_cleanup(ui, config);
 
                     // output directory & browse button on the left and right done through columnss
                     if acui::label_and_browse(ui, "output directory").clicked() {
@@ -240,31 +473,48 @@ impl epi::App for AutocropApp {
                     // if loading images change the text to say how many are loaded
                     let label = {
                         let path_len = tex_manager.input_paths.len();
-                        let image_len = tex_manager.images.len();
 
-                        if path_len != image_len {
-                            format!("{}/{} files loaded", image_len, path_len)
+                        if tex_manager.loaded != tex_manager.to_load {
+                            format!(
+                                "{}/{} files loaded",
+                                tex_manager.loaded, tex_manager.to_load
+                            )
                         } else {
                             format!("{} files selected", path_len)
                         }
                     };
 
                     if acui::label_and_browse(ui, label).clicked() {
-                        let (tx, rx): (Sender<DynamicImage>, Receiver<DynamicImage>) =
-                            mpsc::channel();
+                        let (tx, rx) = mpsc::channel();
                         *reciever = Some(rx);
-                        AutocropApp::open_files(
-                            frame.tex_allocator(),
-                            tex_manager,
-                            tx,
-                            &mut config.input_path,
-                        )
-                        .unwrap_or_else(AutocropApp::dialogue_error);
+                        AutocropApp::open_files(frame.tex_allocator(), tex_manager, tx, config)
+                            .unwrap_or_else(|e| AutocropApp::crop_error_dialog(&e));
                     }
+                    // files that couldn't be opened, left out until they're retried
+                    match acui::failed::draw_failed_files(ui, &tex_manager.failed) {
+                        Some(FailedAction::Retry(i)) => {
+                            let (tx, rx) = mpsc::channel();
+                            *reciever = Some(rx);
+                            tex_manager.retry_failed(i, tx);
+                        }
+                        Some(FailedAction::Remove(i)) => tex_manager.remove_failed(i),
+                        None => {}
+                    }
+                    acui::pad::draw_pad_selector(ui, config);
+                    acui::background::draw_background_selector(ui, config);
                     ui.add_space(5.0);
 
                     // image previewer
-                    acui::previewer::draw_file_previewer(ui, tex_manager);
+                    acui::previewer::draw_file_previewer(ui, tex_manager, config);
+
+                    // rebuilding the originals from a previous crop
+                    ui.add_space(5.0);
+                    if let Some(response) = acui::reconstruct::draw_reconstruct_section(ui) {
+                        if response.clicked() {
+                            AutocropApp::reconstruct(&config.output_path)
+                                .unwrap_or_else(|e| AutocropApp::crop_error_dialog(&e));
+                        }
+                    }
 
                     // lower panel adjustment space, add space to move it to the bottom of the window (when its large enough)
                     let mut add = ui.clip_rect().size().y - ui.min_size().y - LOWER_PANEL_HEIGHT;
@@ -273,46 +523,7 @@ impl epi::App for AutocropApp {
 
                     // crop button
                     if acui::crop_button(ui, config, tex_manager).clicked() {
-                        match crate::crop(tex_manager, config) {
-                            Ok(_) => {
-                                let confirm = MessageDialog::new()
-                                    .set_type(MessageType::Info)
-                                    .set_title("Crop complete!")
-                                    .set_text("Open output now?")
-                                    .show_confirm()
-                                    .unwrap();
-                                if confirm {
-                                    // i have no gaurantee that literally any of these work, if they don't, oh well.
-                                    // todo move to seperate function this is kinda ugly
-                                    use std::process::Command;
-                                    #[cfg(target_os = "windows")]
-                                    let _ = Command::new("explorer")
-                                        .arg(config.output_path.as_os_str())
-                                        .spawn();
-
-                                    #[cfg(target_os = "linux")]
-                                    let _ = Command::new("xdg-open")
-                                        .arg(config.output_path.as_os_str())
-                                        .spawn();
-
-                                    #[cfg(target_os = "macos")]
-                                    let _ = Command::new("open")
-                                        .arg(config.output_path.as_os_str())
-                                        .spawn();
-                                }
-                            }
-                            Err(e) => {
-                                MessageDialog::new()
-                                    .set_type(MessageType::Error)
-                                    .set_title("Error cropping files")
-                                    .set_text(&format!(
-                                        "An error has occured while trying to crop: {}",
-                                        e.to_string()
-                                    ))
-                                    .show_alert()
-                                    .unwrap();
-                            }
-                        }
+                        *crop_job = Some(AutocropApp::start_crop(tex_manager, config));
                     }
                 });
         });
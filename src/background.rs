@@ -0,0 +1,163 @@
+//! Picking the background automatically, either by trying candidates and keeping the one that leaves the least to crop,
+//! or by making a new background out of every image.
+
+use std::borrow::Cow;
+
+use image::{DynamicImage, ImageBuffer, Rgba, RgbaImage};
+
+use crate::compare;
+
+use crate::config::CropType;
+use crate::crop::RectangleRange;
+use crate::error::CropError;
+use crate::progress::{Phase, Progress};
+
+/// Returns the index of the image that, used as the background, gives the smallest total output.
+/// The images must all be the same resolution.
+///
+/// For [`CropType::Rectangle`] that's the area of the range(s) every image is cut to,
+/// for the other crop types it's the number of different pixels.
+/// `changed` says if a pixel is different enough from the background pixel to be kept.
+/// `samples` limits how many evenly spaced candidates are tried, 0 tries every image.
+/// Every candidate tried is a step of [`Phase::Background`], and the search stops if the crop is cancelled.
+pub fn best_background(
+    images: &[DynamicImage],
+    crop_type: CropType,
+    changed: &dyn Fn(Rgba<u8>, Rgba<u8>) -> bool,
+    per_image_range: bool,
+    samples: usize,
+    progress: &Progress,
+) -> Result<usize, CropError> {
+    // converted once, every candidate goes over every image
+    let images: Vec<Cow<'_, RgbaImage>> = images.iter().map(compare::as_rgba8).collect();
+    let mut best = 0;
+    let mut best_score = u64::MAX;
+
+    let candidates = candidates(images.len(), samples);
+    progress.start(Phase::Background, candidates.len());
+    for &candidate in &candidates {
+        progress.check()?;
+        if let Some(score) = score(
+            &images,
+            candidate,
+            crop_type,
+            changed,
+            per_image_range,
+            best_score,
+        ) {
+            if score < best_score {
+                best = candidate;
+                best_score = score;
+            }
+        }
+        progress.step(Phase::Background, candidate, candidates.len());
+    }
+
+    progress.message(&format!("picked image {} as the background", best));
+    Ok(best)
+}
+
+/// Evenly spaced indices to try, always including the first image.
+fn candidates(count: usize, samples: usize) -> Vec<usize> {
+    if samples == 0 || samples >= count {
+        return (0..count).collect();
+    }
+
+    let mut candidates: Vec<usize> = (0..samples).map(|i| i * count / samples).collect();
+    candidates.dedup();
+    candidates
+}
+
+/// Total output size with `candidate` as the background,
+/// or `None` if it's already known to be worse than `limit`.
+fn score(
+    images: &[Cow<'_, RgbaImage>],
+    candidate: usize,
+    crop_type: CropType,
+    changed: &dyn Fn(Rgba<u8>, Rgba<u8>) -> bool,
+    per_image_range: bool,
+    limit: u64,
+) -> Option<u64> {
+    let bg = &images[candidate];
+    let others = || {
+        images
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| *i != candidate)
+    };
+
+    let mut different = 0u64;
+    let mut range = RectangleRange::new();
+    let mut ranges = vec![RectangleRange::new(); images.len()];
+
+    for (x, y, bg_px) in bg.enumerate_pixels() {
+        for (i, image) in others() {
+            if changed(*bg_px, *image.get_pixel(x, y)) {
+                different += 1;
+                range.correct(x, y);
+                ranges[i].correct(x, y);
+            }
+        }
+
+        // only the pixel count can be cut short, ranges can't be known until the end
+        if crop_type != CropType::Rectangle && different >= limit {
+            return None;
+        }
+    }
+
+    let area = |range: &RectangleRange| range.width() as u64 * range.height() as u64;
+    Some(match crop_type {
+        CropType::Rectangle if per_image_range => ranges.iter().map(area).sum(),
+        CropType::Rectangle => area(&range) * (images.len() as u64 - 1),
+        CropType::Exact | CropType::Regions | CropType::Tiles => different,
+    })
+}
+
+/// Builds a background that isn't any one image, out of the median of every image's channels per pixel.
+/// The images must all be the same resolution.
+pub fn median(images: &[DynamicImage]) -> RgbaImage {
+    let images: Vec<Cow<'_, RgbaImage>> = images.iter().map(compare::as_rgba8).collect();
+    let (width, height) = images[0].dimensions();
+    let mut values = vec![0u8; images.len()];
+
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let pixels: Vec<Rgba<u8>> = images.iter().map(|image| *image.get_pixel(x, y)).collect();
+        let mut px = Rgba([0, 0, 0, 0]);
+        for channel in 0..4 {
+            for (value, pixel) in values.iter_mut().zip(&pixels) {
+                *value = pixel[channel];
+            }
+            values.sort_unstable();
+            px[channel] = values[values.len() / 2];
+        }
+        px
+    })
+}
+
+/// Builds a background that isn't any one image, out of the most common color per pixel.
+/// Ties go to whichever color shows up first. The images must all be the same resolution.
+pub fn mode(images: &[DynamicImage]) -> RgbaImage {
+    let images: Vec<Cow<'_, RgbaImage>> = images.iter().map(compare::as_rgba8).collect();
+    let (width, height) = images[0].dimensions();
+    // (color, count), small enough that a linear search beats hashing
+    let mut counts: Vec<(Rgba<u8>, usize)> = vec![];
+
+    ImageBuffer::from_fn(width, height, |x, y| {
+        counts.clear();
+        for image in &images {
+            let px = *image.get_pixel(x, y);
+            match counts.iter_mut().find(|(color, _)| *color == px) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((px, 1)),
+            }
+        }
+
+        let mut best = counts[0];
+        for count in &counts[1..] {
+            if count.1 > best.1 {
+                best = *count;
+            }
+        }
+        best.0
+    })
+}
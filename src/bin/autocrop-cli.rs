@@ -0,0 +1,551 @@
+#![forbid(unsafe_code)]
+#![cfg_attr(not(debug_assertions), deny(warnings))] // Forbid warnings in release builds
+#![warn(clippy::all, rust_2018_idioms)]
+
+//! Headless command line version of autocrop, for when there's no display to open the gui on.
+//! It builds a [`Config`] out of the arguments and runs the same crop as the gui's crop button.
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::process;
+
+use autocrop::pad::size_error;
+use autocrop::reconstruct::{reconstruct_files, reconstruct_manifest, verify};
+use autocrop::{Autocrop, Cluster, Config, MetricType, NameType, Pos2, Progress};
+
+const USAGE: &str = "\
+Usage:
+    autocrop-cli crop [OPTIONS] --output <DIR> <INPUT>...
+    autocrop-cli reconstruct --manifest <FILE> --output <DIR>
+    autocrop-cli reconstruct --background <FILE> --output <DIR> [--offset <X,Y>] <CROPPED>...
+    autocrop-cli help
+    autocrop-cli version
+
+Inputs can be png files or directories, directories are searched (not recursively) for png files.
+The first input image is used as the background unless --background says otherwise.
+
+Crop options:
+    -o, --output <DIR>        Directory to write the cropped images to (created if missing)
+    -b, --background <IMAGE>  Which input is the background, a 0 based index or one of the input files,
+                              or \"best\" to try images and use the one giving the smallest output,
+                              or \"median\"/\"mode\" to make a new background out of every image
+        --background-samples <NUM>
+                              How many images \"best\" tries, 0 for all [default: 8]
+        --pad <ANCHOR>        Pad smaller images with transparency onto a canvas the size of the biggest,
+                              instead of refusing them. ANCHOR is where they go, \"top-left\", \"top\",
+                              \"top-right\", \"left\", \"center\", \"right\", \"bottom-left\", \"bottom\"
+                              or \"bottom-right\". Rebuilt images get their own size back
+        --align <PX>          Search up to this many pixels each way for the shift that lines each image
+                              up with the background, for sprites that moved a little. Not used with
+                              --sequential [default: 0]
+    -g, --group               Split the images into groups that look alike, each cropped against its own
+                              background into its own cluster_N subfolder with its own manifest
+        --group-ratio <NUM>   For --group, an image with at least this part (0 to 1) different from
+                              every group starts its own [default: 0.3]
+    -s, --sequential          Crop every image against the one before it instead of the background,
+                              much smaller for animation. The first image starts the chain
+        --keyframe-ratio <NUM>
+                              For --sequential, keep an image whole when more than this part (0 to 1)
+                              of it changed [default: 0.5]
+    -t, --crop-type <TYPE>    \"rectangle\", \"exact\", \"regions\" or \"tiles\" [default: exact]
+    -l, --leniency <NUM>      How different a pixel has to be to count, in the metric's units [default: 0]
+                              0 to 99.9 % for \"rgb\" and \"linear\", 0 to 255 levels for \"max-channel\",
+                              0 to 100 for \"de76\" and \"de2000\", \"per-channel\" uses --thresholds instead
+        --metric <METRIC>     How pixels are compared, \"rgb\", \"linear\", \"max-channel\", \"per-channel\",
+                              \"de76\" or \"de2000\" [default: rgb]
+        --thresholds <R,G,B,A>
+                              Levels each channel can change by, for \"per-channel\" [default: 0,0,0,0]
+        --alpha <MODE>        How transparency is compared, \"ignore\", \"straight\" or \"premultiplied\"
+                              [default: straight]
+        --smooth <SIGMA>      Blur by this many pixels before comparing, so noise doesn't count as
+                              a change. Outputs are still copied from the originals [default: 0]
+        --min-region <PX>     Drop groups of changed pixels smaller than this, as noise [default: 0]
+        --close <PX>          Fill holes in the changed pixels up to this wide [default: 0]
+        --dilate <PX>         Grow the changed pixels by this much in every direction [default: 0]
+    -r, --resize-output       Shrink the outputs down to the changed area, for \"tiles\" packs the tiles
+    -p, --per-image           Give each image its own range, for \"rectangle\" and \"exact\"
+        --merge-distance <PX> Regions closer than this are merged, for \"regions\" [default: 8]
+        --tile-size <PX>      Width and height of each tile (1 - 1024), for \"tiles\" [default: 16]
+        --bg-name <NAME>      Custom name for the background output [default: original name]
+        --file-name <NAME>    Custom name for the other outputs, numbered [default: original name]
+        --verify              Rebuild the inputs from the outputs in memory and check they match
+    -j, --threads <NUM>       How many threads to crop and save on, 0 for one per core. The outputs
+                              are the same whatever the number [default: 0]
+    -h, --help                Print this message
+
+Every crop also writes a manifest.json next to the outputs, recording where each output goes.
+Images are opened a few at a time and saved as they're cropped, so any number of them fit in
+memory. --group, --verify and the median, mode and best backgrounds open every image at once.
+
+Reconstruct options:
+    -m, --manifest <FILE>     The manifest.json a crop saved, replaces the options below
+    -b, --background <FILE>   The background image the crop saved
+    -o, --output <DIR>        Directory to write the rebuilt images to, must not hold the cropped images
+        --offset <X,Y>        Where resized outputs were cropped from, not needed for full size outputs
+    -h, --help                Print this message";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if let Err(e) = run(args) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+/// Picks the subcommand and hands the rest of the arguments to it.
+fn run(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut args = args.into_iter();
+    match args.next().as_deref() {
+        Some("crop") => crop(args.collect()),
+        Some("reconstruct") => reconstruct(args.collect()),
+        Some("help") | Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some("version") | Some("-V") | Some("--version") => {
+            println!("autocrop {}", env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+        Some(other) => Err(format!("unknown command \"{}\"\n\n{}", other, USAGE).into()),
+        None => Err(format!("missing command\n\n{}", USAGE).into()),
+    }
+}
+
+/// The `crop` subcommand.
+fn crop(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut config = Config::default();
+    let mut inputs: Vec<PathBuf> = vec![];
+    let mut output = None;
+    let mut background = None;
+    let mut check_round_trip = false;
+    let mut leniency_set = false;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            "-o" | "--output" => output = Some(PathBuf::from(value(&arg, args.next())?)),
+            "-b" | "--background" => background = Some(value(&arg, args.next())?),
+            "--background-samples" => {
+                config.background_samples = value(&arg, args.next())?
+                    .parse()
+                    .map_err(|_| format!("{} expects a whole number", arg))?;
+            }
+            "--pad" => {
+                config.anchor = value(&arg, args.next())?.parse()?;
+                config.pad = true;
+            }
+            "--align" => {
+                config.align = value(&arg, args.next())?
+                    .parse()
+                    .map_err(|_| format!("{} expects a whole number of pixels", arg))?;
+            }
+            "-g" | "--group" => config.cluster = true,
+            "--group-ratio" => {
+                config.cluster_ratio = value(&arg, args.next())?
+                    .parse()
+                    .map_err(|_| format!("{} expects a number", arg))?;
+                if !(0.0..=1.0).contains(&config.cluster_ratio) {
+                    return Err(format!("{} must be between 0 and 1", arg).into());
+                }
+            }
+            "-s" | "--sequential" => config.sequential = true,
+            "--keyframe-ratio" => {
+                config.keyframe_ratio = value(&arg, args.next())?
+                    .parse()
+                    .map_err(|_| format!("{} expects a number", arg))?;
+                if !(0.0..=1.0).contains(&config.keyframe_ratio) {
+                    return Err(format!("{} must be between 0 and 1", arg).into());
+                }
+            }
+            "-t" | "--crop-type" => config.crop_type = value(&arg, args.next())?.parse()?,
+            "-l" | "--leniency" => {
+                let leniency: f32 = value(&arg, args.next())?
+                    .parse()
+                    .map_err(|_| format!("{} expects a number", arg))?;
+                if leniency < 0.0 {
+                    return Err(format!("{} can't be negative", arg).into());
+                }
+                config.leniency = leniency;
+                leniency_set = true;
+            }
+            "--metric" => config.metric = value(&arg, args.next())?.parse()?,
+            "--thresholds" => {
+                config.channel_thresholds = parse_thresholds(&value(&arg, args.next())?)?;
+            }
+            "--alpha" => config.alpha_mode = value(&arg, args.next())?.parse()?,
+            "--smooth" => {
+                config.smooth = value(&arg, args.next())?
+                    .parse()
+                    .map_err(|_| format!("{} expects a number", arg))?;
+                if config.smooth < 0.0 {
+                    return Err(format!("{} can't be negative", arg).into());
+                }
+            }
+            "--min-region" => {
+                config.min_region_size = value(&arg, args.next())?
+                    .parse()
+                    .map_err(|_| format!("{} expects a whole number of pixels", arg))?;
+            }
+            "--close" => {
+                config.close = value(&arg, args.next())?
+                    .parse()
+                    .map_err(|_| format!("{} expects a whole number of pixels", arg))?;
+            }
+            "--dilate" => {
+                config.dilate = value(&arg, args.next())?
+                    .parse()
+                    .map_err(|_| format!("{} expects a whole number of pixels", arg))?;
+            }
+            "-r" | "--resize-output" => config.resize_output = true,
+            "-p" | "--per-image" => config.per_image_range = true,
+            "--merge-distance" => {
+                config.merge_distance = value(&arg, args.next())?
+                    .parse()
+                    .map_err(|_| format!("{} expects a whole number of pixels", arg))?;
+            }
+            "--tile-size" => {
+                config.tile_size = value(&arg, args.next())?
+                    .parse()
+                    .map_err(|_| format!("{} expects a whole number of pixels", arg))?;
+                // the same range the gui allows
+                if !(1..=1024).contains(&config.tile_size) {
+                    return Err(format!("{} must be between 1 and 1024", arg).into());
+                }
+            }
+            "--bg-name" => {
+                config.bg_name.name = value(&arg, args.next())?;
+                config.bg_name.name_type = NameType::Custom;
+            }
+            "--file-name" => {
+                config.file_name.name = value(&arg, args.next())?;
+                config.file_name.name_type = NameType::Custom;
+            }
+            "--verify" => check_round_trip = true,
+            "-j" | "--threads" => {
+                let threads: usize = value(&arg, args.next())?
+                    .parse()
+                    .map_err(|_| format!("{} expects a whole number", arg))?;
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build_global()
+                    .map_err(|e| format!("could not start {} threads: {}", threads, e))?;
+            }
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(format!("unknown option \"{}\"\n\n{}", flag, USAGE).into());
+            }
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+
+    config.output_path = output.ok_or("missing --output directory")?;
+    // the per channel thresholds take the place of the leniency, it would do nothing
+    if leniency_set && config.metric == MetricType::PerChannel {
+        return Err(
+            "--leniency isn't used by the \"per-channel\" metric, set how much each channel can change with --thresholds"
+                .into(),
+        );
+    }
+    if config.leniency > config.metric.max_leniency() {
+        return Err(format!(
+            "--leniency can be at most {} {} for the \"{}\" metric",
+            config.metric.max_leniency(),
+            config.metric.unit(),
+            config.metric.name()
+        )
+        .into());
+    }
+    if config.bg_name.is_illegal() {
+        return Err(format!("\"{}\" is not a valid file name", config.bg_name.name).into());
+    }
+    if config.file_name.is_illegal() {
+        return Err(format!("\"{}\" is not a valid file name", config.file_name.name).into());
+    }
+
+    let paths = expand_inputs(inputs)?;
+    if paths.len() < 2 {
+        return Err("at minimum two images must be given".into());
+    }
+    if config.sequential && background.is_some() {
+        return Err(
+            "--background can't be used with --sequential, the first image starts the chain".into(),
+        );
+    }
+    if let Some(background) = background {
+        match background.parse() {
+            Ok(background_type) => config.background_type = background_type,
+            Err(_) => config.background = find_background(&background, &paths)?,
+        }
+    }
+    config.input_path = paths[0].parent().map(PathBuf::from).unwrap_or_default();
+
+    std::fs::create_dir_all(&config.output_path).map_err(|e| {
+        format!(
+            "could not create output directory {}: {}",
+            config.output_path.display(),
+            e
+        )
+    })?;
+
+    // checked from the headers before anything is decoded
+    if !config.pad {
+        let mut sizes = vec![];
+        for path in &paths {
+            let size = image::image_dimensions(path)
+                .map_err(|e| format!("could not open {}: {}", path.display(), e))?;
+            sizes.push(size);
+        }
+        let names: Vec<String> = paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        if let Some(e) = size_error(&names, &sizes) {
+            return Err(format!("{}\nuse --pad to put them on a common canvas", e).into());
+        }
+    }
+
+    let progress = Progress::new(|update| {
+        if update.done > 0 {
+            println!(
+                "{} image {} ({}/{})",
+                update.phase.name(),
+                update.index,
+                update.done,
+                update.total
+            );
+        }
+    })
+    .messages(|message| println!("{}", message));
+    let autocrop = Autocrop::from_config(&config).progress(progress);
+    // saved as it goes, only a few images are ever open at once
+    if !config.cluster && !check_round_trip {
+        autocrop.crop_files_to(
+            &paths,
+            &config.output_path,
+            &config.bg_name,
+            &config.file_name,
+        )?;
+        return Ok(());
+    }
+
+    // grouping and verifying need every image at once
+    let mut images = vec![];
+    for path in &paths {
+        let image =
+            image::open(path).map_err(|e| format!("could not open {}: {}", path.display(), e))?;
+        images.push(image);
+    }
+
+    if config.cluster {
+        let clusters: Vec<Cluster> = autocrop
+            .crop_clusters(&images)?
+            .into_iter()
+            .map(|cluster| cluster.with_sources(&paths))
+            .collect();
+
+        if check_round_trip {
+            for cluster in &clusters {
+                let originals: Vec<_> =
+                    cluster.members.iter().map(|i| images[*i].clone()).collect();
+                if let Some(i) = verify(&cluster.result, &originals)? {
+                    return Err(format!(
+                        "{} does not match its reconstruction, nothing was saved",
+                        paths[cluster.members[i]].display()
+                    )
+                    .into());
+                }
+            }
+            println!("verified: every image reconstructs exactly");
+        }
+
+        for (n, cluster) in clusters.iter().enumerate() {
+            cluster.save(&config.output_path, n, &config.bg_name, &config.file_name)?;
+        }
+        return Ok(());
+    }
+
+    let result = autocrop.crop(&images)?.with_sources(&paths);
+
+    if check_round_trip {
+        if let Some(i) = verify(&result, &images)? {
+            return Err(format!(
+                "{} does not match its reconstruction, nothing was saved",
+                paths[i].display()
+            )
+            .into());
+        }
+        println!("verified: every image reconstructs exactly");
+    }
+
+    result.save(&config.output_path, &config.bg_name, &config.file_name)?;
+    Ok(())
+}
+
+/// The `reconstruct` subcommand.
+fn reconstruct(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut manifest = None;
+    let mut background = None;
+    let mut output = None;
+    let mut offset = None;
+    let mut cropped = vec![];
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            "-m" | "--manifest" => manifest = Some(PathBuf::from(value(&arg, args.next())?)),
+            "-b" | "--background" => background = Some(PathBuf::from(value(&arg, args.next())?)),
+            "-o" | "--output" => output = Some(PathBuf::from(value(&arg, args.next())?)),
+            "--offset" => offset = Some(parse_offset(&value(&arg, args.next())?)?),
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(format!("unknown option \"{}\"\n\n{}", flag, USAGE).into());
+            }
+            _ => cropped.push(PathBuf::from(arg)),
+        }
+    }
+
+    let output = output.ok_or("missing --output directory")?;
+    std::fs::create_dir_all(&output).map_err(|e| {
+        format!(
+            "could not create output directory {}: {}",
+            output.display(),
+            e
+        )
+    })?;
+
+    if let Some(manifest) = manifest {
+        if background.is_some() || offset.is_some() || !cropped.is_empty() {
+            return Err("--manifest already lists the background, offsets and images".into());
+        }
+        for path in reconstruct_manifest(&manifest, &output)? {
+            println!("reconstructed {}", path.display());
+        }
+        return Ok(());
+    }
+
+    let background = background.ok_or("missing --manifest or --background image")?;
+    let cropped = expand_inputs(cropped)?;
+    // the background is usually in the same folder as the cropped images, it doesn't need rebuilding
+    let cropped: Vec<PathBuf> = cropped.into_iter().filter(|p| *p != background).collect();
+    if cropped.is_empty() {
+        return Err("no cropped images given".into());
+    }
+
+    for path in reconstruct_files(&background, &cropped, offset, &output)? {
+        println!("reconstructed {}", path.display());
+    }
+    Ok(())
+}
+
+/// Parses an "x,y" pixel offset.
+fn parse_offset(s: &str) -> Result<Pos2, String> {
+    let error = || format!("offset \"{}\" should look like 12,34", s);
+    let mut parts = s.split(',');
+    let x = parts
+        .next()
+        .ok_or_else(error)?
+        .trim()
+        .parse()
+        .map_err(|_| error())?;
+    let y = parts
+        .next()
+        .ok_or_else(error)?
+        .trim()
+        .parse()
+        .map_err(|_| error())?;
+    if parts.next().is_some() {
+        return Err(error());
+    }
+    Ok(Pos2::new(x, y))
+}
+
+/// Parses `--thresholds R,G,B,A` into a level for each channel.
+fn parse_thresholds(s: &str) -> Result<[u8; 4], String> {
+    let error = || {
+        format!(
+            "thresholds \"{}\" should be four levels from 0 to 255, like 2,2,2,0",
+            s
+        )
+    };
+    let mut thresholds = [0; 4];
+    let mut parts = s.split(',');
+    for threshold in thresholds.iter_mut() {
+        *threshold = parts
+            .next()
+            .ok_or_else(error)?
+            .trim()
+            .parse()
+            .map_err(|_| error())?;
+    }
+    if parts.next().is_some() {
+        return Err(error());
+    }
+    Ok(thresholds)
+}
+
+/// Works out which input the `--background` value means, either an index or a path to one of the inputs.
+fn find_background(background: &str, paths: &[PathBuf]) -> Result<usize, String> {
+    if let Ok(index) = background.parse::<usize>() {
+        return if index < paths.len() {
+            Ok(index)
+        } else {
+            Err(format!(
+                "--background {} is out of range, there are {} images",
+                index,
+                paths.len()
+            ))
+        };
+    }
+
+    let wanted = PathBuf::from(background);
+    let canonical = wanted.canonicalize().ok();
+    paths
+        .iter()
+        .position(|path| {
+            *path == wanted || (canonical.is_some() && path.canonicalize().ok() == canonical)
+        })
+        .ok_or_else(|| format!("--background {} is not one of the input images", background))
+}
+
+/// Returns the value following a flag, or an error naming the flag if there isn't one.
+fn value(flag: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("{} expects a value", flag))
+}
+
+/// Turns the input arguments into a list of image paths.
+/// Files are kept in the order given, directories are replaced with their png files sorted by name.
+fn expand_inputs(inputs: Vec<PathBuf>) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut paths = vec![];
+
+    for input in inputs {
+        if input.is_dir() {
+            let mut found = vec![];
+            for entry in std::fs::read_dir(&input)
+                .map_err(|e| format!("could not read directory {}: {}", input.display(), e))?
+            {
+                let path = entry?.path();
+                let is_png = path
+                    .extension()
+                    .map_or(false, |ext| ext.eq_ignore_ascii_case("png"));
+                if path.is_file() && is_png {
+                    found.push(path);
+                }
+            }
+            found.sort();
+            paths.append(&mut found);
+        } else if input.is_file() {
+            paths.push(input);
+        } else {
+            return Err(format!("{} does not exist", input.display()).into());
+        }
+    }
+
+    Ok(paths)
+}
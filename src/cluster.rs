@@ -0,0 +1,114 @@
+//! Splitting a mixed set of images (different outfits, camera angles) into groups of similar ones,
+//! so each group can be cropped against its own background, see [`Autocrop::crop_clusters`](crate::Autocrop::crop_clusters).
+
+use std::path::{Path, PathBuf};
+
+use image::{imageops, DynamicImage, RgbaImage};
+
+use crate::config::FileName;
+use crate::crop::CropResult;
+use crate::error::CropError;
+use crate::manifest::Manifest;
+use crate::metric::{Metric, Rgb};
+
+/// Images are shrunk down to this size before they're compared.
+const THUMBNAIL_SIZE: u32 = 64;
+/// How different (0 - 1.0, like the leniency) a shrunk down pixel has to be to count as different.
+/// A bit above 0, since shrinking doesn't get rid of all the noise.
+const THUMBNAIL_LENIENCY: f64 = 0.01;
+
+/// A group of similar images and their crop.
+#[derive(Clone, Debug)]
+pub struct Cluster {
+    /// Indices of the input images in the group, in input order.
+    /// The indices in the crop are into this, not the inputs, so the group's manifest stands on its own.
+    pub members: Vec<usize>,
+    pub result: CropResult,
+}
+
+impl Cluster {
+    /// Attaches the input file paths to the outputs, see [`CropResult::with_sources`].
+    pub fn with_sources(mut self, paths: &[PathBuf]) -> Self {
+        let paths: Vec<PathBuf> = self
+            .members
+            .iter()
+            .filter_map(|i| paths.get(*i).cloned())
+            .collect();
+        self.result = self.result.with_sources(&paths);
+        self
+    }
+
+    /// Writes the group's outputs and its manifest into its own subfolder of `dir`, see [`cluster_dir`].
+    pub fn save(
+        &self,
+        dir: &Path,
+        cluster: usize,
+        bg_name: &FileName,
+        file_name: &FileName,
+    ) -> Result<Manifest, CropError> {
+        let dir = cluster_dir(dir, cluster);
+        std::fs::create_dir_all(&dir).map_err(|e| CropError::io(&dir, e))?;
+        self.result.save(&dir, bg_name, file_name)
+    }
+}
+
+/// The subfolder of `dir` the `cluster`th group is saved in.
+pub fn cluster_dir(dir: &Path, cluster: usize) -> PathBuf {
+    dir.join(format!("cluster_{}", cluster))
+}
+
+/// Groups the images by how much of them is different, compared on shrunk down copies.
+///
+/// Each image joins the group whose first image it's closest to, as long as less than `max_ratio` (0 - 1.0)
+/// of it is different, otherwise it starts a new group. An image left alone in its group joins the closest
+/// other group anyway, it would be kept about whole either way.
+///
+/// Returns the indices of every group's images, in input order.
+pub fn find_clusters(images: &[DynamicImage], max_ratio: f32) -> Vec<Vec<usize>> {
+    let thumbnails: Vec<RgbaImage> = images
+        .iter()
+        .map(|image| imageops::thumbnail(image, THUMBNAIL_SIZE, THUMBNAIL_SIZE))
+        .collect();
+
+    let mut clusters: Vec<Vec<usize>> = vec![];
+    for i in 0..thumbnails.len() {
+        match closest(&thumbnails, i, &clusters) {
+            Some((cluster, ratio)) if ratio < max_ratio as f64 => clusters[cluster].push(i),
+            _ => clusters.push(vec![i]),
+        }
+    }
+
+    let (alone, mut clusters): (Vec<_>, Vec<_>) =
+        clusters.into_iter().partition(|cluster| cluster.len() == 1);
+    if clusters.is_empty() {
+        // nothing is like anything else, so grouping doesn't help
+        return vec![(0..images.len()).collect()];
+    }
+    for i in alone.into_iter().flatten() {
+        if let Some((cluster, _)) = closest(&thumbnails, i, &clusters) {
+            clusters[cluster].push(i);
+            clusters[cluster].sort_unstable();
+        }
+    }
+
+    clusters
+}
+
+/// The group whose first image is closest to image `i`, and how much of it is different.
+fn closest(thumbnails: &[RgbaImage], i: usize, clusters: &[Vec<usize>]) -> Option<(usize, f64)> {
+    clusters
+        .iter()
+        .map(|cluster| different_ratio(&thumbnails[cluster[0]], &thumbnails[i]))
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+}
+
+/// Part (0 - 1.0) of the pixels that are different.
+fn different_ratio(thumbnail1: &RgbaImage, thumbnail2: &RgbaImage) -> f64 {
+    let different = thumbnail1
+        .pixels()
+        .zip(thumbnail2.pixels())
+        .filter(|(px1, px2)| Rgb.color_difference(**px1, **px2) > THUMBNAIL_LENIENCY)
+        .count();
+    different as f64 / (THUMBNAIL_SIZE * THUMBNAIL_SIZE) as f64
+}
@@ -0,0 +1,111 @@
+//! The comparison at the heart of a crop: which pixels of an image changed from the background.
+//!
+//! Works straight on the RGBA8 rows of images converted once up front, instead of going through
+//! `GenericImageView::get_pixel` on a [`DynamicImage`], which converts the format
+//! for every pixel. The range and the changed pixels come out of the same pass.
+
+use std::borrow::Cow;
+
+use image::{DynamicImage, Pixel, Rgba, RgbaImage};
+
+use crate::crop::{Pos2, RectangleRange, Shift};
+
+/// What changed in one image.
+#[derive(Clone, Debug, Default)]
+pub struct Differences {
+    /// The changed pixels, top to bottom then left to right. Left empty unless asked for.
+    pub pixels: Vec<Pos2>,
+    /// The range around every changed pixel.
+    pub range: RectangleRange,
+}
+
+/// The image as RGBA8, only converted (copied) if it isn't already.
+pub fn as_rgba8(image: &DynamicImage) -> Cow<'_, RgbaImage> {
+    match image.as_rgba8() {
+        Some(image) => Cow::Borrowed(image),
+        None => Cow::Owned(image.to_rgba8()),
+    }
+}
+
+/// Compares every pixel of `image` against the pixel of `background` it lines up with, see [`Shift`].
+/// `changed` says if a pixel (second) is different enough from the background pixel (first) to be kept,
+/// pixels the moved background doesn't reach are always kept. The changed pixels are only listed
+/// when `keep_pixels` is true, the range is always worked out.
+///
+/// Pixels that are exactly the same are never passed to `changed`, no metric counts them as changed
+/// with a leniency of 0 or more. Rows that are exactly the same are skipped whole.
+pub fn differences<F>(
+    background: &RgbaImage,
+    image: &RgbaImage,
+    shift: Shift,
+    changed: F,
+    keep_pixels: bool,
+) -> Differences
+where
+    F: Fn(Rgba<u8>, Rgba<u8>) -> bool,
+{
+    let width = image.width();
+    let (bg_width, bg_height) = background.dimensions();
+    let row_len = width as usize * 4;
+    let bg_row_len = bg_width as usize * 4;
+    let mut differences = Differences::default();
+    if width == 0 || bg_width == 0 {
+        return differences;
+    }
+
+    for (y, row) in image.as_raw().chunks_exact(row_len).enumerate() {
+        let y = y as u32;
+        // first and last changed pixel of the row, so the range is only corrected twice per row
+        let mut row_min = None;
+        let mut row_max = 0;
+
+        let bg_y = y as i64 - shift.y as i64;
+        let bg_row = if bg_y >= 0 && bg_y < bg_height as i64 {
+            Some(&background.as_raw()[bg_y as usize * bg_row_len..][..bg_row_len])
+        } else {
+            None
+        };
+
+        match bg_row {
+            // nothing to compare if the whole row is the same
+            Some(bg_row) if shift.x == 0 && bg_row == row => continue,
+            Some(bg_row) => {
+                for (x, px) in row.chunks_exact(4).enumerate() {
+                    let bg_x = x as i64 - shift.x as i64;
+                    let different = if bg_x >= 0 && bg_x < bg_width as i64 {
+                        let bg_px = &bg_row[bg_x as usize * 4..][..4];
+                        bg_px != px && changed(*Rgba::from_slice(bg_px), *Rgba::from_slice(px))
+                    } else {
+                        true
+                    };
+
+                    if different {
+                        let x = x as u32;
+                        row_min.get_or_insert(x);
+                        row_max = x;
+                        if keep_pixels {
+                            differences.pixels.push(Pos2::new(x, y));
+                        }
+                    }
+                }
+            }
+            // the moved background doesn't reach this row at all, so all of it is kept
+            None => {
+                row_min = Some(0);
+                row_max = width - 1;
+                if keep_pixels {
+                    differences
+                        .pixels
+                        .extend((0..width).map(|x| Pos2::new(x, y)));
+                }
+            }
+        }
+
+        if let Some(row_min) = row_min {
+            differences.range.correct(row_min, y);
+            differences.range.correct(row_max, y);
+        }
+    }
+
+    differences
+}
@@ -1,14 +1,59 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::str::FromStr;
 
-#[derive(Default, Debug, Serialize, Deserialize)]
+use crate::crop::Pos2;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)] // makes new values default when deserializing old state
 /// Configuration struct, this is whats loaded and saved to keep state.
 /// Also is what is passed down to the crop function.
 pub struct Config {
+    /// Threshold for a pixel to count as changed, in the units of the [`MetricType`].
     pub leniency: f32,
+    /// How pixels are compared.
+    pub metric: MetricType,
+    /// Threshold for each channel (RGBA), only used by [`MetricType::PerChannel`].
+    pub channel_thresholds: [u8; 4],
     pub resize_output: bool,
     pub crop_type: CropType,
+    /// Gap in pixels under which separate regions are merged, only used by [`CropType::Regions`].
+    pub merge_distance: u32,
+    /// Width and height of each tile, only used by [`CropType::Tiles`].
+    pub tile_size: u32,
+    /// Give each image its own range instead of sharing one, for [`CropType::Rectangle`] and [`CropType::Exact`].
+    pub per_image_range: bool,
+    /// How transparency counts towards a pixel being different.
+    pub alpha_mode: AlphaMode,
+    /// Gaussian blur (sigma, in pixels) applied before comparing, 0 doesn't blur.
+    pub smooth: f32,
+    /// Different pixels in connected groups smaller than this are dropped as noise, 0 keeps everything.
+    pub min_region_size: u32,
+    /// Holes in the different pixels up to this many pixels wide are filled in, 0 leaves them.
+    pub close: u32,
+    /// Grows the different pixels by this many pixels in every direction, 0 doesn't grow them.
+    pub dilate: u32,
+    /// Where the background comes from.
+    pub background_type: BackgroundType,
+    /// Index of the loaded image used as the background, for [`BackgroundType::Selected`].
+    /// Reset whenever new images are opened.
+    pub background: usize,
+    /// How many candidates to try for [`BackgroundType::Best`], 0 tries every image.
+    pub background_samples: usize,
+    /// Pad images of different sizes with transparency onto the biggest canvas instead of refusing them.
+    pub pad: bool,
+    /// Where smaller images go on the canvas when padding.
+    pub anchor: Anchor,
+    /// How many pixels each way to search for the shift lining every image up with the background, 0 doesn't align.
+    pub align: u32,
+    /// Crop every image against the one before it instead of the background.
+    pub sequential: bool,
+    /// For sequential crops, part of the canvas (0 - 1.0) that can change before an image is kept whole.
+    pub keyframe_ratio: f32,
+    /// Split the images into groups of similar ones, each with its own background and output subfolder.
+    pub cluster: bool,
+    /// Part of an image (0 - 1.0) that can be different from a group before it starts its own.
+    pub cluster_ratio: f32,
     pub bg_name: FileName,
     pub file_name: FileName,
 
@@ -16,8 +61,43 @@ pub struct Config {
     pub input_path: PathBuf,
 }
 
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            leniency: 0.0,
+            metric: MetricType::default(),
+            channel_thresholds: [0; 4],
+            resize_output: false,
+            crop_type: CropType::default(),
+            merge_distance: 8,
+            tile_size: 16,
+            per_image_range: false,
+            alpha_mode: AlphaMode::default(),
+            smooth: 0.0,
+            min_region_size: 0,
+            close: 0,
+            dilate: 0,
+            background_type: BackgroundType::default(),
+            background: 0,
+            background_samples: 8,
+            pad: false,
+            anchor: Anchor::default(),
+            align: 0,
+            sequential: false,
+            keyframe_ratio: 0.5,
+            cluster: false,
+            cluster_ratio: 0.3,
+            bg_name: FileName::default(),
+            file_name: FileName::default(),
+
+            output_path: PathBuf::default(),
+            input_path: PathBuf::default(),
+        }
+    }
+}
+
 /// Using a "filename" struct so that i can keep the name stored in cache all the time
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FileName {
     pub name_type: NameType,
     pub name: String,
@@ -44,7 +124,7 @@ impl FileName {
 }
 
 /// Enum that represents what the file output names should be.
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum NameType {
     Original,
     Custom,
@@ -73,10 +153,12 @@ impl NameType {
 }
 
 // Selection variant enum for the type of crop that will be done
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum CropType {
     Rectangle,
     Exact,
+    Regions,
+    Tiles,
 }
 
 impl Default for CropType {
@@ -91,6 +173,8 @@ impl CropType {
         match self {
             CropType::Rectangle => "Rectangle",
             CropType::Exact => "Exact Difference",
+            CropType::Regions => "Regions",
+            CropType::Tiles => "Tiles",
         }
     }
 
@@ -99,6 +183,310 @@ impl CropType {
         match self {
             CropType::Rectangle => "Crops out same space in\nall but the background image.",
             CropType::Exact => "Cuts out exact pixels.\nSlower, but snaller. Recommended.",
+            CropType::Regions => {
+                "Cuts out a seperate box around\neach group of changes, per image.\nSmallest when changes are far apart."
+            }
+            CropType::Tiles => {
+                "Keeps whole fixed size tiles that\nhave any change in them.\nLines up with texture and jpeg blocks."
+            }
+        }
+    }
+}
+
+/// Selection variant enum for how pixels are compared, see [`crate::metric`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum MetricType {
+    /// Squared distance in sRGB, the original metric.
+    #[default]
+    Rgb,
+    /// Squared distance in linear light.
+    LinearRgb,
+    /// Biggest difference of any one channel.
+    MaxChannel,
+    /// A threshold for each channel.
+    PerChannel,
+    /// CIE76 color difference in CIELAB.
+    DeltaE76,
+    /// CIEDE2000 color difference in CIELAB.
+    DeltaE2000,
+}
+
+impl MetricType {
+    /// Full name of the enum variant
+    pub fn name(&self) -> &str {
+        match self {
+            MetricType::Rgb => "RGB Distance",
+            MetricType::LinearRgb => "Linear Light",
+            MetricType::MaxChannel => "Max Channel",
+            MetricType::PerChannel => "Per Channel",
+            MetricType::DeltaE76 => "ΔE 1976",
+            MetricType::DeltaE2000 => "ΔE 2000",
+        }
+    }
+
+    /// Tooltip for each enum variant
+    pub fn tooltip(&self) -> &str {
+        match self {
+            MetricType::Rgb => "Squared distance between the colors.\nFast, but dark changes count for\nless than they look.",
+            MetricType::LinearRgb => "Squared distance in linear light,\nhow much light actually changed.",
+            MetricType::MaxChannel => {
+                "Biggest change in any one channel,\nin levels from 0 to 255.\nGood for lossy compression noise."
+            }
+            MetricType::PerChannel => "Each channel gets its own threshold,\nin levels from 0 to 255.",
+            MetricType::DeltaE76 => {
+                "Distance in CIELAB, close to how\ndifferent colors look.\nAround 2.3 is just noticeable."
+            }
+            MetricType::DeltaE2000 => {
+                "Improved CIELAB distance, closest\nto how different colors look.\nSlowest. Around 1 is just noticeable."
+            }
+        }
+    }
+
+    /// Unit the leniency is in, shown next to the slider.
+    pub fn unit(&self) -> &str {
+        match self {
+            MetricType::Rgb | MetricType::LinearRgb => "%",
+            MetricType::MaxChannel | MetricType::PerChannel => "levels",
+            MetricType::DeltaE76 | MetricType::DeltaE2000 => "ΔE",
+        }
+    }
+
+    /// Highest leniency that makes sense.
+    pub fn max_leniency(&self) -> f32 {
+        match self {
+            MetricType::Rgb | MetricType::LinearRgb => 99.9,
+            MetricType::MaxChannel | MetricType::PerChannel => 255.0,
+            MetricType::DeltaE76 | MetricType::DeltaE2000 => 100.0,
+        }
+    }
+}
+
+impl FromStr for MetricType {
+    type Err = String;
+
+    /// Parses the short command line name of the metric, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "rgb" => Ok(MetricType::Rgb),
+            "linear" => Ok(MetricType::LinearRgb),
+            "max-channel" => Ok(MetricType::MaxChannel),
+            "per-channel" => Ok(MetricType::PerChannel),
+            "de76" => Ok(MetricType::DeltaE76),
+            "de2000" => Ok(MetricType::DeltaE2000),
+            _ => Err(format!(
+                "unknown metric \"{}\", expected \"rgb\", \"linear\", \"max-channel\", \"per-channel\", \"de76\" or \"de2000\"",
+                s
+            )),
+        }
+    }
+}
+
+/// Selection variant enum for how alpha is compared
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum AlphaMode {
+    /// Only color is compared, the old behaviour. Changes to transparency alone are missed.
+    Ignore,
+    /// Alpha is compared like a color channel, but the color of two fully transparent pixels isn't.
+    #[default]
+    Straight,
+    /// Colors are multiplied by their alpha before comparing, so faint pixels need bigger changes to count.
+    Premultiplied,
+}
+
+impl AlphaMode {
+    /// Full name of the enum variant
+    pub fn name(&self) -> &str {
+        match self {
+            AlphaMode::Ignore => "Ignore Alpha",
+            AlphaMode::Straight => "Straight Alpha",
+            AlphaMode::Premultiplied => "Premultiplied",
+        }
+    }
+
+    /// Tooltip for each enum variant
+    pub fn tooltip(&self) -> &str {
+        match self {
+            AlphaMode::Ignore => "Only compare color.\nA pixel that only turns transparent\ncounts as the same.",
+            AlphaMode::Straight => {
+                "Compare alpha as well as color.\nTwo invisible pixels are always\nthe same, whatever their color."
+            }
+            AlphaMode::Premultiplied => {
+                "Compare colors scaled by their alpha.\nChanges to faint pixels matter less."
+            }
+        }
+    }
+}
+
+impl FromStr for AlphaMode {
+    type Err = String;
+
+    /// Parses the short command line name of the alpha mode, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ignore" => Ok(AlphaMode::Ignore),
+            "straight" => Ok(AlphaMode::Straight),
+            "premultiplied" | "premul" => Ok(AlphaMode::Premultiplied),
+            _ => Err(format!(
+                "unknown alpha mode \"{}\", expected \"ignore\", \"straight\" or \"premultiplied\"",
+                s
+            )),
+        }
+    }
+}
+
+/// Selection variant enum for where the background image comes from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum BackgroundType {
+    /// The image picked by the user.
+    #[default]
+    Selected,
+    /// Whichever image gives the smallest output.
+    Best,
+    /// A new image made from the median of every image, per pixel and channel.
+    Median,
+    /// A new image made from the most common color of every image, per pixel.
+    Mode,
+}
+
+impl BackgroundType {
+    /// Full name of the enum variant
+    pub fn name(&self) -> &str {
+        match self {
+            BackgroundType::Selected => "Selected Image",
+            BackgroundType::Best => "Best Image",
+            BackgroundType::Median => "Median",
+            BackgroundType::Mode => "Most Common",
+        }
+    }
+
+    /// Tooltip for each enum variant
+    pub fn tooltip(&self) -> &str {
+        match self {
+            BackgroundType::Selected => "Use the highlighted image,\nclick an image to select it.",
+            BackgroundType::Best => {
+                "Try images as the background and use\nwhichever gives the smallest output.\nSlow for big sets."
+            }
+            BackgroundType::Median => {
+                "Make a new background out of the\nmiddle value of every pixel.\nEvery image is saved as a difference."
+            }
+            BackgroundType::Mode => {
+                "Make a new background out of the\nmost common color of every pixel.\nEvery image is saved as a difference."
+            }
+        }
+    }
+
+    /// True if the background is made up rather than one of the images.
+    pub fn is_synthetic(&self) -> bool {
+        matches!(self, BackgroundType::Median | BackgroundType::Mode)
+    }
+}
+
+impl FromStr for BackgroundType {
+    type Err = String;
+
+    /// Parses the short command line name of the background type, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "selected" => Ok(BackgroundType::Selected),
+            "best" => Ok(BackgroundType::Best),
+            "median" => Ok(BackgroundType::Median),
+            "mode" => Ok(BackgroundType::Mode),
+            _ => Err(format!(
+                "unknown background type \"{}\", expected \"selected\", \"best\", \"median\" or \"mode\"",
+                s
+            )),
+        }
+    }
+}
+
+/// Selection variant enum for where a smaller image goes on the shared canvas when padding
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Full name of the enum variant
+    pub fn name(&self) -> &str {
+        match self {
+            Anchor::TopLeft => "Top Left",
+            Anchor::Top => "Top Center",
+            Anchor::TopRight => "Top Right",
+            Anchor::Left => "Middle Left",
+            Anchor::Center => "Center",
+            Anchor::Right => "Middle Right",
+            Anchor::BottomLeft => "Bottom Left",
+            Anchor::Bottom => "Bottom Center",
+            Anchor::BottomRight => "Bottom Right",
+        }
+    }
+
+    /// Where an image of `size` goes on a canvas of `canvas` size, which is at least as big.
+    pub fn offset(&self, size: (u32, u32), canvas: (u32, u32)) -> Pos2 {
+        let (free_x, free_y) = (
+            canvas.0.saturating_sub(size.0),
+            canvas.1.saturating_sub(size.1),
+        );
+        let x = match self {
+            Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => 0,
+            Anchor::Top | Anchor::Center | Anchor::Bottom => free_x / 2,
+            Anchor::TopRight | Anchor::Right | Anchor::BottomRight => free_x,
+        };
+        let y = match self {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => 0,
+            Anchor::Left | Anchor::Center | Anchor::Right => free_y / 2,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => free_y,
+        };
+        Pos2::new(x, y)
+    }
+}
+
+impl FromStr for Anchor {
+    type Err = String;
+
+    /// Parses the short command line name of the anchor, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "top-left" => Ok(Anchor::TopLeft),
+            "top" => Ok(Anchor::Top),
+            "top-right" => Ok(Anchor::TopRight),
+            "left" => Ok(Anchor::Left),
+            "center" | "centre" => Ok(Anchor::Center),
+            "right" => Ok(Anchor::Right),
+            "bottom-left" => Ok(Anchor::BottomLeft),
+            "bottom" => Ok(Anchor::Bottom),
+            "bottom-right" => Ok(Anchor::BottomRight),
+            _ => Err(format!(
+                "unknown anchor \"{}\", expected \"top-left\", \"top\", \"top-right\", \"left\", \"center\", \"right\", \"bottom-left\", \"bottom\" or \"bottom-right\"",
+                s
+            )),
+        }
+    }
+}
+
+impl FromStr for CropType {
+    type Err = String;
+
+    /// Parses the short command line name of the crop type, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "rectangle" | "rect" => Ok(CropType::Rectangle),
+            "exact" => Ok(CropType::Exact),
+            "regions" => Ok(CropType::Regions),
+            "tiles" => Ok(CropType::Tiles),
+            _ => Err(format!(
+                "unknown crop type \"{}\", expected \"rectangle\", \"exact\", \"regions\" or \"tiles\"",
+                s
+            )),
         }
     }
 }
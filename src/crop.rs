@@ -0,0 +1,1852 @@
+//! The crop engine itself, usable without the gui.
+//!
+//! [`Autocrop`] takes the crop options, compares every image against the background (the first image),
+//! and hands back a [`CropResult`] holding the cropped images in memory.
+//! Writing them to disk is a separate step, see [`CropResult::save`].
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use image::png::{CompressionType, FilterType, PngEncoder};
+use image::{ColorType, DynamicImage, GenericImageView, ImageBuffer, Rgba, RgbaImage};
+use rayon::prelude::*;
+
+use crate::align;
+use crate::background;
+use crate::cluster::{self, Cluster};
+use crate::compare::{self, Differences};
+use crate::config::{
+    AlphaMode, Anchor, BackgroundType, Config, CropType, FileName, MetricType, NameType,
+};
+use crate::error::CropError;
+use crate::manifest::{Manifest, ManifestEntry, MANIFEST_FILE_NAME, MANIFEST_VERSION};
+use crate::mask;
+use crate::metric;
+use crate::pad;
+use crate::progress::{Phase, Progress};
+use crate::tile::{self, Tile};
+
+/// A pixel position on the image canvas.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Pos2 {
+    pub x: u32,
+    pub y: u32,
+}
+
+impl Pos2 {
+    pub fn new(x: u32, y: u32) -> Self {
+        Self { x, y }
+    }
+}
+
+/// How far an image was moved to line up with the background, see [`Autocrop::align`].
+/// Pixel (x, y) of the image lines up with pixel (x - shift.x, y - shift.y) of the background.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Shift {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Shift {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+}
+
+/// An inclusive rectangle of pixels, grown one pixel at a time with [`RectangleRange::correct`].
+#[derive(Clone, Debug)]
+pub struct RectangleRange {
+    pub min: Pos2,
+    pub max: Pos2,
+}
+
+impl RectangleRange {
+    /// Creates an empty range, it contains nothing until it has been corrected at least once.
+    pub fn new() -> Self {
+        Self {
+            min: Pos2::new(u32::MAX, u32::MAX),
+            max: Pos2::new(0, 0),
+        }
+    }
+
+    /// True if no pixel has been added to the range yet.
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y
+    }
+
+    /// Width in pixels, both edges included.
+    pub fn width(&self) -> u32 {
+        if self.is_empty() {
+            0
+        } else {
+            self.max.x - self.min.x + 1
+        }
+    }
+
+    /// Height in pixels, both edges included.
+    pub fn height(&self) -> u32 {
+        if self.is_empty() {
+            0
+        } else {
+            self.max.y - self.min.y + 1
+        }
+    }
+
+    /// Grows the range to include the pixel, returns true if the range changed.
+    pub fn correct(&mut self, x: u32, y: u32) -> bool {
+        let mut result = false;
+        if x < self.min.x {
+            self.min.x = x;
+            result = true;
+        }
+        if y < self.min.y {
+            self.min.y = y;
+            result = true;
+        }
+
+        if x > self.max.x {
+            self.max.x = x;
+            result = true;
+        }
+        if y > self.max.y {
+            self.max.y = y;
+            result = true;
+        }
+
+        result
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.min.x && x <= self.max.x && y >= self.min.y && y <= self.max.y
+    }
+
+    /// Grows the range to cover the other one as well.
+    pub fn union(&mut self, other: &RectangleRange) {
+        if !other.is_empty() {
+            self.correct(other.min.x, other.min.y);
+            self.correct(other.max.x, other.max.y);
+        }
+    }
+
+    /// True if there are at most `distance` pixels of gap between the two ranges on both axes.
+    /// Overlapping ranges are always near.
+    pub fn near(&self, other: &RectangleRange, distance: u32) -> bool {
+        fn gap(min1: u32, max1: u32, min2: u32, max2: u32) -> u32 {
+            if max1 < min2 {
+                min2 - max1 - 1
+            } else if max2 < min1 {
+                min1 - max2 - 1
+            } else {
+                0
+            }
+        }
+
+        gap(self.min.x, self.max.x, other.min.x, other.max.x) <= distance
+            && gap(self.min.y, self.max.y, other.min.y, other.max.y) <= distance
+    }
+}
+
+impl Default for RectangleRange {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builder for a crop, holds every option that changes how images get compared and cut out.
+///
+/// ```no_run
+/// use autocrop::{Autocrop, CropType, FileName};
+/// use std::path::{Path, PathBuf};
+///
+/// let paths = vec![PathBuf::from("idle.png"), PathBuf::from("blink.png")];
+/// let result = Autocrop::new()
+///     .crop_type(CropType::Rectangle)
+///     .leniency(0.5)
+///     .resize_output(true)
+///     .crop_files(&paths)?;
+///
+/// for output in &result.outputs {
+///     println!("{} at {:?}", output.index, output.offset);
+/// }
+/// result.save(Path::new("out"), &FileName::default(), &FileName::default())?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Autocrop {
+    leniency: f32,
+    metric: MetricType,
+    channel_thresholds: [u8; 4],
+    crop_type: CropType,
+    resize_output: bool,
+    merge_distance: u32,
+    tile_size: u32,
+    per_image_range: bool,
+    alpha_mode: AlphaMode,
+    smooth: f32,
+    min_region_size: u32,
+    close: u32,
+    dilate: u32,
+    background_type: BackgroundType,
+    background: usize,
+    background_samples: usize,
+    pad: bool,
+    anchor: Anchor,
+    align: u32,
+    sequential: bool,
+    keyframe_ratio: f32,
+    cluster_ratio: f32,
+    progress: Progress,
+}
+
+impl Default for Autocrop {
+    // built from the config so the library and the gui can't drift apart
+    fn default() -> Self {
+        Self::from_config(&Config::default())
+    }
+}
+
+impl Autocrop {
+    /// The same defaults as the gui: exact crop, no leniency, outputs kept at full size,
+    /// 16 pixel tiles, regions merged within 8 pixels, keyframes past half the canvas
+    /// and groups split at 0.3 different.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes the crop options out of the gui config.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            leniency: config.leniency,
+            metric: config.metric,
+            channel_thresholds: config.channel_thresholds,
+            crop_type: config.crop_type,
+            resize_output: config.resize_output,
+            merge_distance: config.merge_distance,
+            tile_size: config.tile_size,
+            per_image_range: config.per_image_range,
+            alpha_mode: config.alpha_mode,
+            smooth: config.smooth,
+            min_region_size: config.min_region_size,
+            close: config.close,
+            dilate: config.dilate,
+            background_type: config.background_type,
+            background: config.background,
+            background_samples: config.background_samples,
+            pad: config.pad,
+            anchor: config.anchor,
+            align: config.align,
+            sequential: config.sequential,
+            keyframe_ratio: config.keyframe_ratio,
+            cluster_ratio: config.cluster_ratio,
+            progress: Progress::default(),
+        }
+    }
+
+    /// How different a pixel has to be from the background before it's kept,
+    /// in the units of the metric, see [`MetricType::unit`].
+    pub fn leniency(mut self, leniency: f32) -> Self {
+        self.leniency = leniency;
+        self
+    }
+
+    /// How pixels are compared, see [`crate::metric`].
+    pub fn metric(mut self, metric: MetricType) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    /// Threshold for each channel (RGBA), only used by [`MetricType::PerChannel`].
+    pub fn channel_thresholds(mut self, channel_thresholds: [u8; 4]) -> Self {
+        self.channel_thresholds = channel_thresholds;
+        self
+    }
+
+    /// How transparency counts towards a pixel being different, see [`AlphaMode`].
+    pub fn alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
+        self.alpha_mode = alpha_mode;
+        self
+    }
+
+    /// Blurs the images by this much (the gaussian sigma, in pixels) before comparing them, 0 doesn't blur.
+    /// Only decides which pixels changed, the outputs are still cut from the unblurred images.
+    pub fn smooth(mut self, smooth: f32) -> Self {
+        self.smooth = smooth;
+        self
+    }
+
+    /// Different pixels in connected groups smaller than this are dropped as noise, 0 keeps everything.
+    pub fn min_region_size(mut self, min_region_size: u32) -> Self {
+        self.min_region_size = min_region_size;
+        self
+    }
+
+    /// Holes in the different pixels up to this many pixels wide are filled in, 0 leaves them.
+    pub fn close(mut self, close: u32) -> Self {
+        self.close = close;
+        self
+    }
+
+    /// Grows the different pixels by this many pixels in every direction, 0 doesn't grow them.
+    pub fn dilate(mut self, dilate: u32) -> Self {
+        self.dilate = dilate;
+        self
+    }
+
+    pub fn crop_type(mut self, crop_type: CropType) -> Self {
+        self.crop_type = crop_type;
+        self
+    }
+
+    /// When true the outputs are shrunk down to the changed area, see [`CroppedImage::offset`].
+    pub fn resize_output(mut self, resize_output: bool) -> Self {
+        self.resize_output = resize_output;
+        self
+    }
+
+    /// For [`CropType::Regions`], boxes with at most this many pixels of gap between them become one region.
+    /// 8 by default, 0 only merges boxes that touch.
+    pub fn merge_distance(mut self, merge_distance: u32) -> Self {
+        self.merge_distance = merge_distance;
+        self
+    }
+
+    /// For [`CropType::Tiles`], the width and height of each tile in pixels.
+    pub fn tile_size(mut self, tile_size: u32) -> Self {
+        self.tile_size = tile_size;
+        self
+    }
+
+    /// For [`CropType::Rectangle`] and [`CropType::Exact`], gives every image its own tight range
+    /// instead of one range shared by all of them. Each output's offset is recorded as usual.
+    pub fn per_image_range(mut self, per_image_range: bool) -> Self {
+        self.per_image_range = per_image_range;
+        self
+    }
+
+    /// Index of the image everything else is compared against, the first image by default.
+    /// Only used with [`BackgroundType::Selected`].
+    pub fn background(mut self, background: usize) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Where the background comes from, see [`BackgroundType`].
+    /// [`BackgroundType::Best`] is slow, every candidate is compared against every image.
+    pub fn background_type(mut self, background_type: BackgroundType) -> Self {
+        self.background_type = background_type;
+        self
+    }
+
+    /// How many evenly spaced candidates [`BackgroundType::Best`] tries, 0 tries every image.
+    pub fn background_samples(mut self, background_samples: usize) -> Self {
+        self.background_samples = background_samples;
+        self
+    }
+
+    /// When true, images smaller than the biggest one are padded with transparency onto a canvas its size
+    /// instead of being refused. Outputs are cropped on the canvas, the original sizes are kept for reconstructing.
+    pub fn pad(mut self, pad: bool) -> Self {
+        self.pad = pad;
+        self
+    }
+
+    /// Where smaller images go on the canvas when padding, see [`Autocrop::pad`].
+    pub fn anchor(mut self, anchor: Anchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Searches up to this many pixels each way for the shift that best lines every image up with the background,
+    /// for sprites that moved a little between exports. 0 turns it off. Not used for sequential crops.
+    pub fn align(mut self, align: u32) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// When true every image is cropped against the rebuilt image before it instead of the background,
+    /// which is much smaller for animation. The first image starts the chain, the background options aren't used.
+    pub fn sequential(mut self, sequential: bool) -> Self {
+        self.sequential = sequential;
+        self
+    }
+
+    /// For sequential crops, an image with more than this part (0 - 1.0) of the canvas changed
+    /// is output whole as a keyframe instead, and the chain starts over from it.
+    pub fn keyframe_ratio(mut self, keyframe_ratio: f32) -> Self {
+        self.keyframe_ratio = keyframe_ratio;
+        self
+    }
+
+    /// For [`Autocrop::crop_clusters`], images with at least this part (0 - 1.0) different
+    /// from every group start a group of their own.
+    /// 0.3 by default, 0 puts every image in one group since none of them can join another.
+    pub fn cluster_ratio(mut self, cluster_ratio: f32) -> Self {
+        self.cluster_ratio = cluster_ratio;
+        self
+    }
+
+    /// Where the crop reports how far along it is, and the token that stops it part way, see [`Progress`].
+    /// A cancelled crop returns an error, outputs it already saved are left as they are.
+    pub fn progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Opens every image in `paths` and crops them.
+    /// The paths are kept on the outputs so they can be saved under their original names.
+    pub fn crop_files(&self, paths: &[PathBuf]) -> Result<CropResult, CropError> {
+        let mut images = vec![];
+        self.progress.start(Phase::Opening, paths.len());
+        for (i, path) in paths.iter().enumerate() {
+            self.progress.check()?;
+            let image = image::open(path).map_err(|e| CropError::decode(path, e))?;
+            images.push(image);
+            self.progress.step(Phase::Opening, i, paths.len());
+        }
+
+        // checked here too, so the error can name the files
+        if !self.pad {
+            let names: Vec<String> = paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            let sizes: Vec<(u32, u32)> = images.iter().map(|image| image.dimensions()).collect();
+            if let Some(e) = pad::size_error(&names, &sizes) {
+                return Err(e);
+            }
+        }
+
+        Ok(self.crop(&images)?.with_sources(paths))
+    }
+
+    /// Crops the images in `paths` straight into `dir`, the same as [`Autocrop::crop_files`] then [`CropResult::save`]
+    /// but without ever holding every image at once. Each image is opened when it's needed and dropped
+    /// as soon as its outputs are saved, so memory goes with how big the images are, not how many there are.
+    /// Images are opened twice, once to find the range and again to cut and save them,
+    /// sequential crops only once.
+    ///
+    /// The median and most common color backgrounds, and picking the best one, are worked out from every image together,
+    /// so those still open them all up front.
+    pub fn crop_files_to(
+        &self,
+        paths: &[PathBuf],
+        dir: &Path,
+        bg_name: &FileName,
+        file_name: &FileName,
+    ) -> Result<Manifest, CropError> {
+        if paths.len() < 2 {
+            return Err("At minimum two images must be selected.".into());
+        }
+        if self.crop_type == CropType::Tiles && self.tile_size == 0 {
+            return Err("The tile size must be at least 1 pixel.".into());
+        }
+        check_names(bg_name, file_name)?;
+        if !self.sequential && self.background_type != BackgroundType::Selected {
+            self.progress
+                .message("this background needs every image at once, opening them all");
+            return self.crop_files(paths)?.save_with_progress(
+                dir,
+                bg_name,
+                file_name,
+                &self.progress,
+            );
+        }
+        let bg_index = if self.sequential { 0 } else { self.background };
+        if bg_index >= paths.len() {
+            return Err(format!(
+                "The background is image {}, but there are only {} images.",
+                bg_index,
+                paths.len()
+            )
+            .into());
+        }
+        // every output is named before anything is opened, so a clash is found before anything is written
+        let named: Vec<CroppedImage> = paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let mut output = if i == bg_index {
+                    CroppedImage::background(RgbaImage::new(0, 0), i)
+                } else {
+                    CroppedImage::new(RgbaImage::new(0, 0), i)
+                };
+                output.source = Some(path.clone());
+                if self.crop_type == CropType::Regions && i != bg_index {
+                    output.region = Some(0);
+                }
+                output
+            })
+            .collect();
+        check_collisions(&named, bg_name, file_name)?;
+        // only the first region of every input could be named up front,
+        // the rest are checked as they're saved so none of them overwrites another output
+        let (_, bg_output) = output_name(&named[bg_index], bg_name, file_name)?;
+        let mut saved = OutputNames::default();
+        saved.claim(&bg_output)?;
+        let saved = Mutex::new(saved);
+
+        // the sizes are read from the headers, nothing is decoded yet
+        let mut sizes = vec![];
+        for path in paths {
+            let size = image::image_dimensions(path).map_err(|e| CropError::decode(path, e))?;
+            sizes.push(size);
+        }
+        let padded = sizes.windows(2).any(|pair| pair[0] != pair[1]);
+        let canvas = if self.pad {
+            sizes.iter().fold((0, 0), |(width, height), size| {
+                (width.max(size.0), height.max(size.1))
+            })
+        } else {
+            let names: Vec<String> = paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            if let Some(e) = pad::size_error(&names, &sizes) {
+                return Err(e);
+            }
+            sizes[0]
+        };
+        if padded {
+            self.progress.message(&format!(
+                "padding the images onto a {}x{} canvas",
+                canvas.0, canvas.1
+            ));
+        }
+
+        // opens an image, padded onto the canvas if it needs to be
+        let open = |i: usize| -> Result<DynamicImage, CropError> {
+            let image = image::open(&paths[i]).map_err(|e| CropError::decode(&paths[i], e))?;
+            if image.dimensions() == canvas {
+                Ok(image)
+            } else {
+                Ok(pad::pad_image(&image, canvas, self.anchor))
+            }
+        };
+        // what crop_files and crop set on every output
+        let save = |mut output: CroppedImage| -> Result<ManifestEntry, CropError> {
+            output.source = paths.get(output.index).cloned();
+            if padded {
+                output.original_size = sizes.get(output.index).copied();
+            }
+            if !output.is_background {
+                let (_, output_name) = output_name(&output, bg_name, file_name)?;
+                saved.lock().unwrap().claim(&output_name)?;
+            }
+            save_output(&output, dir, bg_name, file_name, &self.progress)
+        };
+        let changed = self.changed();
+
+        let (mut result, entries) = if self.sequential {
+            let frames = (0..paths.len()).map(|i| Ok(Cow::Owned(open(i)?)));
+            let mut entries = vec![];
+            let result = self.crop_sequence(frames, paths.len(), &changed, &mut |output| {
+                entries.push(save(output)?);
+                Ok(())
+            })?;
+            (result, entries)
+        } else {
+            let bg = self.compared(&open(bg_index)?).into_owned();
+
+            // first pass, only the shift and range of every image are kept
+            self.progress.start(Phase::Comparing, paths.len());
+            let found = (0..paths.len())
+                .into_par_iter()
+                .map(|i| {
+                    self.progress.check()?;
+                    let mut found = (Shift::default(), RectangleRange::new());
+                    if i != bg_index {
+                        let image = open(i)?;
+                        let image = self.compared(&image);
+                        let shift = self.find_shift(&bg, &image, &changed);
+                        let range = self
+                            .find_differences(&bg, &image, shift, &changed, false)
+                            .range;
+                        found = (shift, range);
+                    }
+                    self.progress.step(Phase::Comparing, i, paths.len());
+                    Ok(found)
+                })
+                .collect::<Result<Vec<(Shift, RectangleRange)>, CropError>>()?;
+
+            let mut range = RectangleRange::new();
+            for (_, image_range) in &found {
+                range.union(image_range);
+            }
+
+            // second pass, the pixels are found again for the crop types that need them
+            let keep_pixels = self.keeps_pixels();
+            self.progress.start(Phase::Saving, paths.len());
+            let entries = (0..paths.len())
+                .into_par_iter()
+                .map(|i| {
+                    self.progress.check()?;
+                    let image = open(i)?;
+                    let outputs = if i == bg_index {
+                        vec![CroppedImage::background(image.into_rgba8(), i)]
+                    } else {
+                        let (shift, image_range) = &found[i];
+                        let image_range = if self.per_image_range {
+                            image_range
+                        } else {
+                            &range
+                        };
+                        let pixels = if keep_pixels {
+                            let compared = self.compared(&image);
+                            self.find_differences(&bg, &compared, *shift, &changed, true)
+                                .pixels
+                        } else {
+                            vec![]
+                        };
+                        let mut outputs = self.crop_image(&image, i, &pixels, image_range);
+                        for output in &mut outputs {
+                            output.shift = *shift;
+                        }
+                        outputs
+                    };
+                    let entries = outputs.into_iter().map(save).collect();
+                    self.progress.step(Phase::Saving, i, paths.len());
+                    entries
+                })
+                .collect::<Result<Vec<Vec<ManifestEntry>>, CropError>>()?
+                .into_iter()
+                .flatten()
+                .collect();
+
+            let result = CropResult {
+                outputs: vec![],
+                inputs: paths.len(),
+                width: canvas.0,
+                height: canvas.1,
+                range: if range.is_empty() { None } else { Some(range) },
+                crop_type: self.crop_type,
+                leniency: self.leniency,
+                metric: self.metric,
+                channel_thresholds: self.channel_thresholds,
+                alpha_mode: self.alpha_mode,
+                resize_output: self.resize_output,
+                per_image_range: self.per_image_range,
+                sequential: false,
+                anchor: Anchor::default(),
+            };
+            (result, entries)
+        };
+        result.anchor = self.anchor;
+
+        let manifest = result.manifest(entries);
+        manifest.save(&dir.join(MANIFEST_FILE_NAME))?;
+        Ok(manifest)
+    }
+
+    /// Crops the images against the background, which is the first image unless [`Autocrop::background`] says otherwise.
+    /// Images must all be the same resolution, unless [`Autocrop::pad`] is set.
+    pub fn crop(&self, images: &[DynamicImage]) -> Result<CropResult, CropError> {
+        if images.len() < 2 {
+            return Err("At minimum two images must be selected.".into());
+        }
+        if self.crop_type == CropType::Tiles && self.tile_size == 0 {
+            return Err("The tile size must be at least 1 pixel.".into());
+        }
+
+        // every image has to line up pixel for pixel with eachother
+        let sizes: Vec<(u32, u32)> = images.iter().map(|image| image.dimensions()).collect();
+        let padded = self.padded(images)?;
+        let mut result = match &padded {
+            Some(padded) => {
+                let (width, height) = padded[0].dimensions();
+                self.progress.message(&format!(
+                    "padded the images onto a {}x{} canvas",
+                    width, height
+                ));
+                self.crop_canvas(padded)?
+            }
+            None => self.crop_canvas(images)?,
+        };
+        if padded.is_some() {
+            for output in &mut result.outputs {
+                // a made up background has no original size, it's made the size of the canvas
+                output.original_size = sizes.get(output.index).copied();
+            }
+        }
+        result.anchor = self.anchor;
+        Ok(result)
+    }
+
+    /// The images padded onto a shared canvas, `None` if they didn't need it.
+    /// Without [`Autocrop::pad`], images of different sizes are an error listing every size.
+    fn padded(&self, images: &[DynamicImage]) -> Result<Option<Vec<DynamicImage>>, CropError> {
+        if self.pad {
+            return Ok(pad::pad_images(images, self.anchor));
+        }
+
+        let names: Vec<String> = (0..images.len()).map(|i| format!("image {}", i)).collect();
+        let sizes: Vec<(u32, u32)> = images.iter().map(|image| image.dimensions()).collect();
+        match pad::size_error(&names, &sizes) {
+            Some(e) => Err(e),
+            None => Ok(None),
+        }
+    }
+
+    /// The crop itself, on images that are all the same size.
+    fn crop_canvas(&self, images: &[DynamicImage]) -> Result<CropResult, CropError> {
+        let (width, height) = images[0].dimensions();
+        let changed = self.changed();
+
+        if self.sequential {
+            let mut outputs = vec![];
+            let frames = images.iter().map(|image| Ok(Cow::Borrowed(image)));
+            let mut result = self.crop_sequence(frames, images.len(), &changed, &mut |output| {
+                outputs.push(output);
+                Ok(())
+            })?;
+            result.outputs = outputs;
+            return Ok(result);
+        }
+
+        // comparing blurred copies stops noise from counting as a change, the outputs are still cut from the originals
+        let smoothed: Vec<DynamicImage>;
+        let compared = if self.smooth > 0.0 {
+            self.progress
+                .message("smoothing the images before comparing");
+            smoothed = images
+                .par_iter()
+                .map(|image| smooth(image, self.smooth))
+                .collect();
+            &smoothed[..]
+        } else {
+            images
+        };
+
+        // a made up background isn't one of the images, so it gets the index after the last image
+        let synthetic = match self.background_type {
+            BackgroundType::Median => {
+                self.progress.message("making the median background");
+                Some(DynamicImage::ImageRgba8(background::median(images)))
+            }
+            BackgroundType::Mode => {
+                self.progress
+                    .message("making the most common color background");
+                Some(DynamicImage::ImageRgba8(background::mode(images)))
+            }
+            BackgroundType::Selected | BackgroundType::Best => None,
+        };
+        let bg_index = match self.background_type {
+            BackgroundType::Selected => self.background,
+            BackgroundType::Best => background::best_background(
+                compared,
+                self.crop_type,
+                &changed,
+                self.per_image_range,
+                self.background_samples,
+                &self.progress,
+            )?,
+            BackgroundType::Median | BackgroundType::Mode => images.len(),
+        };
+        let smoothed_synthetic;
+        let bg = match &synthetic {
+            Some(synthetic) if self.smooth > 0.0 => {
+                smoothed_synthetic = smooth(synthetic, self.smooth);
+                &smoothed_synthetic
+            }
+            Some(synthetic) => synthetic,
+            None => compared.get(bg_index).ok_or_else(|| {
+                format!(
+                    "The background is image {}, but there are only {} images.",
+                    bg_index,
+                    images.len()
+                )
+            })?,
+        };
+        let bg = compare::as_rgba8(bg);
+        let keep_pixels = self.keeps_pixels();
+
+        if self.align > 0 {
+            self.progress
+                .message("lining the images up with the background first");
+        }
+        if self.cleans_up() {
+            self.progress.message("cleaning up the masks as well");
+        }
+        // a single pass per image, every image on its own thread: how far it's moved to line up
+        // with the background (the background itself stays put), then its range and different pixels
+        self.progress.start(Phase::Comparing, images.len());
+        let differences = (0..images.len())
+            .into_par_iter()
+            .map(|i| {
+                self.progress.check()?;
+                let mut found = (Shift::default(), Differences::default());
+                if i != bg_index {
+                    let image = compare::as_rgba8(&compared[i]);
+                    let shift = self.find_shift(&bg, &image, &changed);
+                    let image_differences =
+                        self.find_differences(&bg, &image, shift, &changed, keep_pixels);
+                    found = (shift, image_differences);
+                }
+                self.progress.step(Phase::Comparing, i, images.len());
+                Ok(found)
+            })
+            .collect::<Result<Vec<(Shift, Differences)>, CropError>>()?;
+        let shifts: Vec<Shift> = differences.iter().map(|(shift, _)| *shift).collect();
+
+        // get range of crop area
+        let mut range = RectangleRange::new();
+        for (_, image_differences) in &differences {
+            range.union(&image_differences.range);
+        }
+
+        // every image's own range, only kept when asked for
+        let mut ranges: Vec<RectangleRange> = vec![];
+        if self.per_image_range {
+            ranges = differences
+                .iter()
+                .map(|(_, image_differences)| image_differences.range.clone())
+                .collect();
+        }
+
+        // first vec is for images, second is for groups of everydifferent pixel
+        // the background's stays empty, since everything is compared against it
+        let mut different_pixels: Vec<Vec<Pos2>> = vec![];
+        if keep_pixels {
+            different_pixels = differences
+                .into_iter()
+                .map(|(_, image_differences)| image_differences.pixels)
+                .collect();
+        }
+
+        let mut outputs = vec![];
+        if let Some(synthetic) = synthetic {
+            outputs.push(CroppedImage::background(synthetic.into_rgba8(), bg_index));
+        }
+
+        // cropping here, every image on its own thread but put back in input order
+        self.progress.start(Phase::Cropping, images.len());
+        let cropped = images
+            .par_iter()
+            .enumerate()
+            .map(|(i, image)| {
+                self.progress.check()?;
+                // if background, return itself
+                let image_outputs = if i == bg_index {
+                    vec![CroppedImage::background(image.to_rgba8(), i)]
+                } else {
+                    // the range this image is cut down to
+                    let image_range = if self.per_image_range {
+                        &ranges[i]
+                    } else {
+                        &range
+                    };
+                    // rectangles don't need the exact pixels, unless the mask was cleaned up
+                    let pixels = different_pixels
+                        .get(i)
+                        .map_or(&[][..], |pixels| &pixels[..]);
+                    let mut image_outputs = self.crop_image(image, i, pixels, image_range);
+                    for output in &mut image_outputs {
+                        output.shift = shifts[i];
+                    }
+                    image_outputs
+                };
+                self.progress.step(Phase::Cropping, i, images.len());
+                Ok(image_outputs)
+            })
+            .collect::<Result<Vec<Vec<CroppedImage>>, CropError>>()?;
+        outputs.extend(cropped.into_iter().flatten());
+
+        Ok(CropResult {
+            outputs,
+            inputs: images.len(),
+            width,
+            height,
+            range: if range.is_empty() { None } else { Some(range) },
+            crop_type: self.crop_type,
+            leniency: self.leniency,
+            metric: self.metric,
+            channel_thresholds: self.channel_thresholds,
+            alpha_mode: self.alpha_mode,
+            resize_output: self.resize_output,
+            per_image_range: self.per_image_range,
+            sequential: false,
+            anchor: Anchor::default(),
+        })
+    }
+}
+
+impl Autocrop {
+    /// True if the pixel (second) is different enough from the background pixel (first) to be kept.
+    fn changed(&self) -> impl Fn(Rgba<u8>, Rgba<u8>) -> bool + Sync {
+        let metric = metric::metric(self.metric, self.channel_thresholds);
+        let threshold = metric.threshold(self.leniency);
+        let alpha_mode = self.alpha_mode;
+        move |bg_px, px| metric.difference(bg_px, px, alpha_mode) > threshold
+    }
+
+    /// The image as it's compared, blurred if [`Autocrop::smooth`] is set.
+    fn compared<'a>(&self, image: &'a DynamicImage) -> Cow<'a, RgbaImage> {
+        if self.smooth > 0.0 {
+            Cow::Owned(smooth(image, self.smooth).into_rgba8())
+        } else {
+            compare::as_rgba8(image)
+        }
+    }
+
+    /// True if the mask of different pixels is cleaned up, see [`mask::cleanup`].
+    fn cleans_up(&self) -> bool {
+        self.min_region_size > 1 || self.close > 0 || self.dilate > 0
+    }
+
+    /// True if the exact different pixels are needed, not just the range.
+    /// Exact, regions and tiles cut with them, cleaning up the mask needs them even for rectangles.
+    fn keeps_pixels(&self) -> bool {
+        self.cleans_up()
+            || matches!(
+                self.crop_type,
+                CropType::Exact | CropType::Regions | CropType::Tiles
+            )
+    }
+
+    /// How far `image` is moved to line up with the background, see [`Autocrop::align`].
+    fn find_shift<F>(&self, bg: &RgbaImage, image: &RgbaImage, changed: &F) -> Shift
+    where
+        F: Fn(Rgba<u8>, Rgba<u8>) -> bool,
+    {
+        if self.align > 0 {
+            align::find_shift(bg, image, self.align, changed)
+        } else {
+            Shift::default()
+        }
+    }
+
+    /// What changed in `image` against the background, see [`compare::differences`], with the mask cleaned up.
+    /// The pixels are always found when cleaning up, the cleaned up range depends on them.
+    fn find_differences<F>(
+        &self,
+        bg: &RgbaImage,
+        image: &RgbaImage,
+        shift: Shift,
+        changed: &F,
+        keep_pixels: bool,
+    ) -> Differences
+    where
+        F: Fn(Rgba<u8>, Rgba<u8>) -> bool + ?Sized,
+    {
+        let cleanup = self.cleans_up();
+        let mut differences =
+            compare::differences(bg, image, shift, changed, keep_pixels || cleanup);
+        if cleanup {
+            differences.pixels = mask::cleanup(
+                &differences.pixels,
+                image.width(),
+                image.height(),
+                self.min_region_size,
+                self.close,
+                self.dilate,
+            );
+            // what the moved background doesn't reach was kept because there's nothing under it,
+            // not because it changed, so it can't be noise. Dropping it would leave a hole in the rebuilt image
+            if shift != Shift::default() {
+                differences.pixels.extend(align::uncovered(
+                    image.width(),
+                    image.height(),
+                    bg.dimensions(),
+                    shift,
+                ));
+                differences
+                    .pixels
+                    .sort_unstable_by_key(|pos| (pos.y, pos.x));
+                differences.pixels.dedup();
+            }
+            // the cleaned up pixels can reach past the old range, so it's worked out again from them
+            differences.range = RectangleRange::new();
+            for Pos2 { x, y } in &differences.pixels {
+                differences.range.correct(*x, *y);
+            }
+        }
+        differences
+    }
+
+    /// Splits the images into groups of similar ones, see [`cluster::find_clusters`],
+    /// and crops every group on its own. The background options apply within each group,
+    /// a selected background that isn't in a group is swapped for the group's first image.
+    pub fn crop_clusters(&self, images: &[DynamicImage]) -> Result<Vec<Cluster>, CropError> {
+        if images.len() < 2 {
+            return Err("At minimum two images must be selected.".into());
+        }
+
+        // grouping compares images pixel for pixel too, each group is then padded onto its own canvas
+        let padded = self.padded(images)?;
+
+        self.progress.message("grouping similar images");
+        let mut clusters = vec![];
+        for (n, members) in
+            cluster::find_clusters(padded.as_deref().unwrap_or(images), self.cluster_ratio)
+                .into_iter()
+                .enumerate()
+        {
+            self.progress
+                .message(&format!("cropping group {}, images {:?}", n, members));
+            let group: Vec<DynamicImage> = members.iter().map(|i| images[*i].clone()).collect();
+            let background = members
+                .iter()
+                .position(|i| *i == self.background)
+                .unwrap_or(0);
+            let result = self.clone().background(background).crop(&group)?;
+            clusters.push(Cluster { members, result });
+        }
+
+        Ok(clusters)
+    }
+
+    /// Crops every image against the rebuilt image before it, see [`Autocrop::sequential`].
+    /// Only ever holds one frame and the rebuilt one before it, so `frames` can be decoded one by one
+    /// as they're needed. Every output is handed to `sink` as soon as it's cut, in order,
+    /// so the returned result has no outputs of its own. `total` is how many frames there are, for the progress.
+    fn crop_sequence<'a>(
+        &self,
+        mut frames: impl Iterator<Item = Result<Cow<'a, DynamicImage>, CropError>>,
+        total: usize,
+        changed: &dyn Fn(Rgba<u8>, Rgba<u8>) -> bool,
+        sink: &mut dyn FnMut(CroppedImage) -> Result<(), CropError>,
+    ) -> Result<CropResult, CropError> {
+        self.progress.start(Phase::Cropping, total);
+        self.progress.check()?;
+        let first = frames
+            .next()
+            .ok_or("At minimum two images must be selected.")??;
+        let (width, height) = first.dimensions();
+        let cleanup = self.cleans_up();
+
+        // the previous image as it will be rebuilt, which isn't quite the original with any leniency
+        let mut previous = first.to_rgba8();
+        sink(CroppedImage::background(previous.clone(), 0))?;
+        drop(first);
+        self.progress.step(Phase::Cropping, 0, total);
+        let mut range = RectangleRange::new();
+        let mut inputs = 1;
+
+        for (i, image) in frames.enumerate().map(|(i, image)| (i + 1, image)) {
+            self.progress.check()?;
+            let image = image?;
+            inputs += 1;
+            let smoothed;
+            let (reference, compared) = if self.smooth > 0.0 {
+                smoothed = (
+                    image::imageops::blur(&previous, self.smooth),
+                    smooth(&image, self.smooth),
+                );
+                (&smoothed.0, &smoothed.1)
+            } else {
+                (&previous, &*image)
+            };
+
+            let image_differences = compare::differences(
+                reference,
+                &compare::as_rgba8(compared),
+                Shift::default(),
+                changed,
+                true,
+            );
+            let mut pixels = image_differences.pixels;
+            if cleanup {
+                pixels = mask::cleanup(
+                    &pixels,
+                    width,
+                    height,
+                    self.min_region_size,
+                    self.close,
+                    self.dilate,
+                );
+            }
+            let mut image_range = RectangleRange::new();
+            for Pos2 { x, y } in &pixels {
+                image_range.correct(*x, *y);
+            }
+
+            // rectangles keep their whole range, everything else just the pixels
+            let kept_area = match self.crop_type {
+                CropType::Rectangle => image_range.width() as f64 * image_range.height() as f64,
+                CropType::Exact | CropType::Regions | CropType::Tiles => pixels.len() as f64,
+            };
+            if kept_area / (width as f64 * height as f64) > self.keyframe_ratio as f64 {
+                self.progress.message(&format!(
+                    "image {} changed too much, keeping it whole as a keyframe",
+                    i
+                ));
+                previous = image.to_rgba8();
+                sink(CroppedImage {
+                    keyframe: true,
+                    ..CroppedImage::new(previous.clone(), i)
+                })?;
+                self.progress.step(Phase::Cropping, i, total);
+                continue;
+            }
+
+            for mut output in self.crop_image(&image, i, &pixels, &image_range) {
+                output.base = Some(i - 1);
+                // the next image is cropped against this one as it will be rebuilt
+                if output.tiles.is_empty() {
+                    crate::reconstruct::place(&mut previous, &output.image, output.offset)?;
+                } else {
+                    crate::reconstruct::place_tiles(&mut previous, &output.image, &output.tiles)?;
+                }
+                sink(output)?;
+            }
+            range.union(&image_range);
+            self.progress.step(Phase::Cropping, i, total);
+        }
+
+        Ok(CropResult {
+            outputs: vec![],
+            inputs,
+            width,
+            height,
+            range: if range.is_empty() { None } else { Some(range) },
+            crop_type: self.crop_type,
+            leniency: self.leniency,
+            metric: self.metric,
+            channel_thresholds: self.channel_thresholds,
+            alpha_mode: self.alpha_mode,
+            resize_output: self.resize_output,
+            per_image_range: true,
+            sequential: true,
+            anchor: Anchor::default(),
+        })
+    }
+
+    /// Cuts one image down to what's kept of it, `pixels` being its different pixels (not needed for rectangles)
+    /// and `image_range` the range it's cut to. Usually gives one output, but regions can give several.
+    fn crop_image(
+        &self,
+        image: &DynamicImage,
+        index: usize,
+        pixels: &[Pos2],
+        image_range: &RectangleRange,
+    ) -> Vec<CroppedImage> {
+        // converted once up front, getting pixels one by one out of any other format converts every pixel
+        let image = compare::as_rgba8(image);
+        let image = &*image;
+        let (width, height) = image.dimensions();
+        // full size image with only the kept pixels copied over, everything else transparent
+        let full: RgbaImage = match self.crop_type {
+            CropType::Rectangle => ImageBuffer::from_fn(width, height, |x, y| {
+                if image_range.contains(x, y) {
+                    kept(*image.get_pixel(x, y))
+                } else {
+                    Rgba([0, 0, 0, 0])
+                }
+            }),
+            CropType::Exact => {
+                let mut new = ImageBuffer::new(width, height);
+                // for every different position on the image, copy it over
+                for Pos2 { x, y } in pixels.iter() {
+                    new.put_pixel(*x, *y, kept(*image.get_pixel(*x, *y)));
+                }
+                new
+            }
+            CropType::Regions => return self.crop_regions(image, index, pixels),
+            CropType::Tiles => {
+                let tiles = tile::find_tiles(pixels, width, height, self.tile_size);
+                if !self.resize_output {
+                    tile::sparse(image, &tiles, self.tile_size)
+                } else {
+                    // resizing packs the kept tiles together instead of cropping to the range
+                    if tiles.is_empty() {
+                        return vec![CroppedImage::unchanged(index)];
+                    }
+                    let (img, tiles) = tile::pack(image, &tiles, self.tile_size);
+                    return vec![CroppedImage {
+                        tiles,
+                        ..CroppedImage::new(img, index)
+                    }];
+                }
+            }
+        };
+
+        if !self.resize_output {
+            return vec![CroppedImage::new(full, index)];
+        }
+        if image_range.is_empty() {
+            return vec![CroppedImage::unchanged(index)];
+        }
+        let img = image::imageops::crop_imm(
+            &full,
+            image_range.min.x,
+            image_range.min.y,
+            image_range.width(),
+            image_range.height(),
+        )
+        .to_image();
+        vec![CroppedImage {
+            offset: image_range.min,
+            ..CroppedImage::new(img, index)
+        }]
+    }
+
+    /// Cuts a tight output out for every separate region of different pixels in one image.
+    /// Only the different pixels are copied, the rest of each region is left transparent.
+    fn crop_regions(&self, image: &RgbaImage, index: usize, pixels: &[Pos2]) -> Vec<CroppedImage> {
+        let (width, height) = image.dimensions();
+        let regions = crate::region::find_regions(pixels, width, height, self.merge_distance);
+
+        if regions.is_empty() {
+            return vec![CroppedImage {
+                region: Some(0),
+                ..CroppedImage::unchanged(index)
+            }];
+        }
+
+        let mut outputs: Vec<CroppedImage> = regions
+            .iter()
+            .enumerate()
+            .map(|(region, range)| CroppedImage {
+                offset: range.min,
+                region: Some(region),
+                ..CroppedImage::new(RgbaImage::new(range.width(), range.height()), index)
+            })
+            .collect();
+
+        for Pos2 { x, y } in pixels {
+            // regions don't overlap once merged, so the pixel is in exactly one of them
+            if let Some(output) = regions
+                .iter()
+                .position(|range| range.contains(*x, *y))
+                .map(|region| &mut outputs[region])
+            {
+                output.image.put_pixel(
+                    x - output.offset.x,
+                    y - output.offset.y,
+                    kept(*image.get_pixel(*x, *y)),
+                );
+            }
+        }
+
+        outputs
+    }
+}
+
+/// A single cropped output image, along with where it belongs on the original canvas.
+#[derive(Clone, Debug)]
+pub struct CroppedImage {
+    /// The cropped pixels, anything that wasn't kept is fully transparent.
+    pub image: RgbaImage,
+    /// Index of the input image this was cropped from.
+    pub index: usize,
+    /// Top left corner of this image on the original canvas, (0, 0) unless the output was resized.
+    pub offset: Pos2,
+    /// Which region of the input this is, only set for [`CropType::Regions`] where an input can have several outputs.
+    pub region: Option<usize>,
+    /// For packed [`CropType::Tiles`] outputs, where each tile was taken from and put.
+    /// When this isn't empty the image is the packed tiles rather than a piece of the canvas.
+    pub tiles: Vec<Tile>,
+    /// The file the input was loaded from, if it came from one.
+    pub source: Option<PathBuf>,
+    /// For sequential crops, the input this goes on top of once that's been rebuilt, always an earlier one.
+    /// `None` means the background.
+    pub base: Option<usize>,
+    /// How far the input was moved to line up with the background, see [`Autocrop::align`].
+    /// Only what's under the output is moved, the output itself goes at its offset as usual.
+    pub shift: Shift,
+    /// For sequential crops, true if the input changed too much and was output whole instead,
+    /// it doesn't go on top of anything.
+    pub keyframe: bool,
+    /// Size of the input before it was padded onto the canvas, see [`Autocrop::pad`].
+    /// `None` if the inputs didn't need padding.
+    pub original_size: Option<(u32, u32)>,
+    /// True for the background, which is output whole.
+    pub is_background: bool,
+}
+
+impl CroppedImage {
+    /// An output of input `index` that goes at (0, 0), with nothing else set.
+    pub fn new(image: RgbaImage, index: usize) -> Self {
+        Self {
+            image,
+            index,
+            offset: Pos2::default(),
+            region: None,
+            tiles: vec![],
+            source: None,
+            base: None,
+            shift: Shift::default(),
+            keyframe: false,
+            original_size: None,
+            is_background: false,
+        }
+    }
+
+    /// The background output of input `index`, or of a made up background.
+    pub fn background(image: RgbaImage, index: usize) -> Self {
+        Self {
+            is_background: true,
+            ..Self::new(image, index)
+        }
+    }
+
+    /// The resized output of an input where nothing changed,
+    /// a single clear pixel that still composites back to the background.
+    fn unchanged(index: usize) -> Self {
+        Self::new(RgbaImage::new(1, 1), index)
+    }
+}
+
+/// Everything a crop produced, nothing is written to disk until [`CropResult::save`] is called.
+#[derive(Clone, Debug)]
+pub struct CropResult {
+    /// The outputs in input order, the background is the one marked [`CroppedImage::is_background`].
+    /// Usually one per input, but [`CropType::Regions`] can give several per input.
+    pub outputs: Vec<CroppedImage>,
+    /// How many images were cropped.
+    /// A made up background has this as its index, since it isn't one of the inputs.
+    pub inputs: usize,
+    /// Width of the original canvas.
+    pub width: u32,
+    /// Height of the original canvas.
+    pub height: u32,
+    /// The area that differs in any image, `None` if every image matched the background.
+    pub range: Option<RectangleRange>,
+    /// The options the crop was done with, recorded in the manifest.
+    pub crop_type: CropType,
+    pub leniency: f32,
+    pub metric: MetricType,
+    pub channel_thresholds: [u8; 4],
+    pub alpha_mode: AlphaMode,
+    pub resize_output: bool,
+    pub per_image_range: bool,
+    /// True if every image was cropped against the one before it, see [`Autocrop::sequential`].
+    pub sequential: bool,
+    /// Where smaller inputs were put on the canvas, only matters for outputs with [`CroppedImage::original_size`].
+    pub anchor: Anchor,
+}
+
+impl CropResult {
+    /// Index of the input that was used as the background, `None` if the background was made up.
+    pub fn background(&self) -> Option<usize> {
+        self.outputs
+            .iter()
+            .find(|output| output.is_background)
+            .map(|output| output.index)
+            .filter(|index| *index < self.inputs)
+    }
+
+    /// Attaches the input file paths to the outputs, used for [`NameType::Original`] naming.
+    pub fn with_sources(mut self, paths: &[PathBuf]) -> Self {
+        for output in &mut self.outputs {
+            output.source = paths.get(output.index).cloned();
+        }
+        self
+    }
+
+    /// Writes every output into `dir` as a png, along with a [`Manifest`] of where each one goes.
+    /// Returns the manifest that was written.
+    pub fn save(
+        &self,
+        dir: &Path,
+        bg_name: &FileName,
+        file_name: &FileName,
+    ) -> Result<Manifest, CropError> {
+        self.save_with_progress(dir, bg_name, file_name, &Progress::default())
+    }
+
+    /// [`CropResult::save`], reporting every output saved to `progress` and stopping if it's cancelled.
+    pub fn save_with_progress(
+        &self,
+        dir: &Path,
+        bg_name: &FileName,
+        file_name: &FileName,
+        progress: &Progress,
+    ) -> Result<Manifest, CropError> {
+        check_names(bg_name, file_name)?;
+        check_collisions(&self.outputs, bg_name, file_name)?;
+        // encoding is the slow part, so every output is encoded on its own thread
+        progress.start(Phase::Saving, self.outputs.len());
+        let entries = self
+            .outputs
+            .par_iter()
+            .enumerate()
+            .map(|(i, output)| {
+                let entry = save_output(output, dir, bg_name, file_name, progress)?;
+                progress.step(Phase::Saving, i, self.outputs.len());
+                Ok(entry)
+            })
+            .collect::<Result<Vec<_>, CropError>>()?;
+
+        let manifest = self.manifest(entries);
+        manifest.save(&dir.join(MANIFEST_FILE_NAME))?;
+        Ok(manifest)
+    }
+
+    /// The manifest for outputs saved as `entries`, in order.
+    /// Only goes by the entries, so it works for crops saved as they went too.
+    pub(crate) fn manifest(&self, entries: Vec<ManifestEntry>) -> Manifest {
+        Manifest {
+            version: MANIFEST_VERSION,
+            width: self.width,
+            height: self.height,
+            crop_type: self.crop_type,
+            leniency: self.leniency,
+            metric: self.metric,
+            channel_thresholds: self.channel_thresholds,
+            alpha_mode: self.alpha_mode,
+            resize_output: self.resize_output,
+            per_image_range: self.per_image_range,
+            sequential: self.sequential,
+            anchor: self.anchor,
+            synthetic_background: !entries
+                .iter()
+                .any(|entry| entry.is_background && entry.index < self.inputs),
+            background: entries
+                .iter()
+                .find(|entry| entry.is_background)
+                .map(|entry| entry.output.clone())
+                .unwrap_or_default(),
+            outputs: entries,
+        }
+    }
+}
+
+/// An error if either custom name can't be a file name, checked before anything is written.
+fn check_names(bg_name: &FileName, file_name: &FileName) -> Result<(), CropError> {
+    for naming in [bg_name, file_name].iter() {
+        if naming.is_illegal() {
+            return Err(CropError::IllegalName {
+                name: naming.name.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The name of an output without the extension, and the file it's saved as.
+fn output_name(
+    output: &CroppedImage,
+    bg_name: &FileName,
+    file_name: &FileName,
+) -> Result<(String, PathBuf), CropError> {
+    let naming = if output.is_background {
+        bg_name
+    } else {
+        file_name
+    };
+
+    // file name (without extension)
+    let name = match naming.name_type {
+        // a made up background has no original to be named after
+        NameType::Original if output.is_background && output.source.is_none() => {
+            Cow::from("background")
+        }
+        NameType::Original => {
+            let original_path = output
+                .source
+                .as_ref()
+                .ok_or("Could not retrieve original file name.")?;
+            original_path
+                .file_stem()
+                .unwrap_or(
+                    original_path
+                        .file_name()
+                        .ok_or("Could not retrieve original file name.")?,
+                )
+                .to_string_lossy()
+        }
+        NameType::Custom => {
+            if output.is_background {
+                Cow::from(&naming.name)
+            } else {
+                Cow::from(format!("{}{}", &naming.name, output.index))
+            }
+        }
+    };
+
+    // inputs split into regions get one file per region
+    let output_name = match output.region {
+        Some(region) => PathBuf::from(format!("{}_{}.png", name, region)),
+        None => PathBuf::from(format!("{}.png", name)),
+    };
+    Ok((name.into_owned(), output_name))
+}
+
+/// An error if two of the outputs would be saved under the same name, checked before anything is written.
+/// Happens when a made up background or a custom background name matches one of the inputs' names,
+/// or two inputs from different folders share a name.
+fn check_collisions<'a>(
+    outputs: impl IntoIterator<Item = &'a CroppedImage>,
+    bg_name: &FileName,
+    file_name: &FileName,
+) -> Result<(), CropError> {
+    let mut names = OutputNames::default();
+    for output in outputs {
+        let (_, output_name) = output_name(output, bg_name, file_name)?;
+        names.claim(&output_name)?;
+    }
+    Ok(())
+}
+
+/// The file names outputs are saved under so far.
+#[derive(Default)]
+struct OutputNames(HashSet<String>);
+
+impl OutputNames {
+    /// An error if another output is already saved as `output_name`.
+    fn claim(&mut self, output_name: &Path) -> Result<(), CropError> {
+        // some file systems don't tell upper and lower case apart
+        if self.0.insert(output_name.to_string_lossy().to_lowercase()) {
+            Ok(())
+        } else {
+            Err(CropError::IllegalName {
+                name: output_name.display().to_string(),
+            })
+        }
+    }
+}
+
+/// Names a single output, writes it into `dir` as a png and returns its manifest entry.
+/// The bytes written are counted in `progress`, nothing is written if it's been cancelled.
+fn save_output(
+    output: &CroppedImage,
+    dir: &Path,
+    bg_name: &FileName,
+    file_name: &FileName,
+    progress: &Progress,
+) -> Result<ManifestEntry, CropError> {
+    progress.check()?;
+    let (name, output_name) = output_name(output, bg_name, file_name)?;
+
+    let path = dir.join(&output_name);
+    save_png(&output.image, &path)?;
+    if let Ok(metadata) = std::fs::metadata(&path) {
+        progress.wrote(metadata.len());
+    }
+
+    Ok(ManifestEntry {
+        source: output.source.clone(),
+        output: output_name,
+        name,
+        region: output.region,
+        index: output.index,
+        x: output.offset.x,
+        y: output.offset.y,
+        width: output.image.width(),
+        height: output.image.height(),
+        tiles: output.tiles.clone(),
+        base: output.base,
+        keyframe: output.keyframe,
+        shift_x: output.shift.x,
+        shift_y: output.shift.y,
+        original_width: output.original_size.map(|(width, _)| width),
+        original_height: output.original_size.map(|(_, height)| height),
+        is_background: output.is_background,
+    })
+}
+
+/// Gaussian blur of the image, only used for comparing.
+fn smooth(image: &DynamicImage, sigma: f32) -> DynamicImage {
+    DynamicImage::ImageRgba8(image::imageops::blur(image, sigma))
+}
+
+/// Encodes the image as a png at `path`.
+pub(crate) fn save_png(img: &RgbaImage, path: &Path) -> Result<(), CropError> {
+    // compression (this doesn't do much, but doesn't seem to massively impact performance either)
+    let buffer = File::create(path).map_err(|e| CropError::io(path, e))?;
+    let encoder = PngEncoder::new_with_quality(buffer, CompressionType::Rle, FilterType::Paeth);
+    encoder
+        .encode(img, img.width(), img.height(), ColorType::Rgba8)
+        .map_err(|e| match e {
+            image::ImageError::IoError(e) => CropError::io(path, e),
+            e => CropError::io(path, io::Error::other(e)),
+        })?;
+    Ok(())
+}
+
+/// What a kept pixel that is fully transparent *and* black is saved as.
+/// Cropped out space is saved as fully transparent black, so a kept pixel like that would be mistaken for it
+/// and the background would show through on reconstruction. It's invisible either way.
+pub const CLEARED: Rgba<u8> = Rgba([255, 0, 255, 0]);
+
+/// The pixel as it's saved into an output, see [`CLEARED`].
+pub(crate) fn kept(px: Rgba<u8>) -> Rgba<u8> {
+    if px == Rgba([0, 0, 0, 0]) {
+        CLEARED
+    } else {
+        px
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reconstruct::verify;
+
+    /// A background with no two pixels alike in a row, so there's only one shift that lines it up.
+    fn pattern(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([(x * 13) as u8, (y * 29) as u8, (x * y % 251) as u8, 255])
+        })
+    }
+
+    fn pool(threads: usize) -> rayon::ThreadPool {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn threads_dont_change_the_crop() {
+        // every image changes somewhere else, in two places so regions splits them
+        let bg = pattern(48, 32);
+        let images: Vec<DynamicImage> = (0..12)
+            .map(|i| {
+                let mut image = bg.clone();
+                for y in 0..4 {
+                    for x in 0..4 {
+                        image.put_pixel(i * 3 + x, y + i, Rgba([255, 255, 255, 255]));
+                        image.put_pixel(44 - i * 3 + x, 28 - i + y, Rgba([0, 0, 0, 0]));
+                    }
+                }
+                DynamicImage::ImageRgba8(image)
+            })
+            .collect();
+        let paths: Vec<PathBuf> = (0..images.len())
+            .map(|i| PathBuf::from(format!("image{}.png", i)))
+            .collect();
+
+        for crop_type in [
+            CropType::Rectangle,
+            CropType::Exact,
+            CropType::Regions,
+            CropType::Tiles,
+        ] {
+            let autocrop = Autocrop::new()
+                .crop_type(crop_type)
+                .resize_output(true)
+                .per_image_range(true);
+            let crop = |threads| {
+                pool(threads)
+                    .install(|| autocrop.crop(&images))
+                    .unwrap()
+                    .with_sources(&paths)
+            };
+            let (one, many) = (crop(1), crop(8));
+
+            assert_eq!(one.outputs.len(), many.outputs.len(), "{:?}", crop_type);
+            for (one, many) in one.outputs.iter().zip(&many.outputs) {
+                assert_eq!(one.index, many.index);
+                assert_eq!(one.offset, many.offset);
+                assert_eq!(one.region, many.region);
+                assert_eq!(one.tiles, many.tiles);
+                assert!(
+                    one.image == many.image,
+                    "{:?} image {}",
+                    crop_type,
+                    one.index
+                );
+            }
+
+            // saved on as many threads too, the manifests list the outputs in the same order
+            let dir =
+                std::env::temp_dir().join(format!("autocrop-threads-test-{}", std::process::id()));
+            let save = |threads: usize, result: &CropResult| {
+                let dir = dir.join(threads.to_string());
+                std::fs::create_dir_all(&dir).unwrap();
+                pool(threads)
+                    .install(|| result.save(&dir, &FileName::default(), &FileName::default()))
+                    .unwrap()
+            };
+            let one_manifest = save(1, &one);
+            let many_manifest = save(8, &many);
+            assert_eq!(
+                serde_json::to_string(&one_manifest).unwrap(),
+                serde_json::to_string(&many_manifest).unwrap(),
+                "{:?}",
+                crop_type
+            );
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn align_keeps_uncovered_pixels_through_min_region() {
+        let bg = pattern(16, 8);
+        // moved 2 pixels right, the 2 x 8 strip on the left has nothing under it
+        let moved = RgbaImage::from_fn(16, 8, |x, y| {
+            if x < 2 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                *bg.get_pixel(x - 2, y)
+            }
+        });
+        let images = vec![
+            DynamicImage::ImageRgba8(bg),
+            DynamicImage::ImageRgba8(moved),
+        ];
+
+        for crop_type in [
+            CropType::Rectangle,
+            CropType::Exact,
+            CropType::Regions,
+            CropType::Tiles,
+        ] {
+            for resize_output in [false, true] {
+                let result = Autocrop::new()
+                    .crop_type(crop_type)
+                    .resize_output(resize_output)
+                    .align(2)
+                    // bigger than the strip, which would be dropped as noise
+                    .min_region_size(20)
+                    .crop(&images)
+                    .unwrap();
+                assert_eq!(result.outputs[1].shift, Shift::new(2, 0));
+                assert_eq!(
+                    verify(&result, &images).unwrap(),
+                    None,
+                    "{:?}, resized {}",
+                    crop_type,
+                    resize_output
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn saving_as_it_goes_refuses_a_region_named_like_the_background() {
+        let dir = std::env::temp_dir().join(format!("autocrop-names-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // two changes far apart, so the input is split into a_0 and a_1
+        let bg = pattern(48, 32);
+        let mut changed = bg.clone();
+        changed.put_pixel(2, 2, Rgba([255, 255, 255, 255]));
+        changed.put_pixel(44, 28, Rgba([255, 255, 255, 255]));
+        let paths = vec![dir.join("a_1.png"), dir.join("a.png")];
+        bg.save(&paths[0]).unwrap();
+        changed.save(&paths[1]).unwrap();
+
+        let out = dir.join("out");
+        std::fs::create_dir_all(&out).unwrap();
+        let custom = |name: &str| FileName {
+            name_type: NameType::Custom,
+            name: name.to_string(),
+        };
+        for (bg_name, file_name, clash) in [
+            (FileName::default(), FileName::default(), "a_1.png"),
+            (custom("name1_1"), custom("name"), "name1_1.png"),
+        ] {
+            let autocrop = Autocrop::new().crop_type(CropType::Regions);
+            // the crop itself is fine, it's only the names that clash
+            assert!(autocrop.crop_files(&paths).is_ok());
+
+            match autocrop.crop_files_to(&paths, &out, &bg_name, &file_name) {
+                Err(CropError::IllegalName { name }) => assert_eq!(name, clash),
+                result => panic!("{} wasn't refused: {:?}", clash, result),
+            }
+            // whatever got written first, the background wasn't overwritten by the region
+            if let Ok(written) = image::open(out.join(clash)) {
+                assert!(written.to_rgba8() == bg, "{} was overwritten", clash);
+            }
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saving_as_it_goes_matches_cropping_then_saving() {
+        let dir = std::env::temp_dir().join(format!("autocrop-stream-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let bg = pattern(48, 32);
+        let mut images = vec![bg.clone()];
+        // two changes far apart, then the same moved a pixel right and down, then a clear block
+        let mut changed = bg.clone();
+        for y in 0..4 {
+            for x in 0..4 {
+                changed.put_pixel(x + 3, y + 3, Rgba([255, 255, 255, 255]));
+                changed.put_pixel(x + 40, y + 24, Rgba([0, 0, 255, 128]));
+            }
+        }
+        images.push(changed.clone());
+        images.push(RgbaImage::from_fn(48, 32, |x, y| {
+            *changed.get_pixel(x.saturating_sub(1), y.saturating_sub(1))
+        }));
+        let mut clear = bg.clone();
+        for y in 10..20 {
+            for x in 20..30 {
+                clear.put_pixel(x, y, Rgba([0, 0, 0, 0]));
+            }
+        }
+        images.push(clear);
+        // only padded crops get the smaller one
+        images.push(image::imageops::crop_imm(&changed, 4, 4, 40, 24).to_image());
+        let paths: Vec<PathBuf> = images
+            .iter()
+            .enumerate()
+            .map(|(i, image)| {
+                let path = dir.join(format!("image{}.png", i));
+                image.save(&path).unwrap();
+                path
+            })
+            .collect();
+
+        type Set = fn(Autocrop) -> Autocrop;
+        let options: [(&str, Set); 5] = [
+            ("plain", |autocrop| autocrop),
+            ("per image", |autocrop| autocrop.per_image_range(true)),
+            ("aligned", |autocrop| autocrop.align(2)),
+            ("sequential", |autocrop| autocrop.sequential(true)),
+            ("padded", |autocrop| {
+                autocrop.pad(true).anchor(Anchor::Center)
+            }),
+        ];
+        for crop_type in [
+            CropType::Rectangle,
+            CropType::Exact,
+            CropType::Regions,
+            CropType::Tiles,
+        ] {
+            for (option, set) in options.iter() {
+                for resize_output in [false, true] {
+                    let autocrop = set(Autocrop::new()
+                        .crop_type(crop_type)
+                        .resize_output(resize_output)
+                        .tile_size(4)
+                        .background(1));
+                    let paths = if *option == "padded" {
+                        &paths[..]
+                    } else {
+                        &paths[..paths.len() - 1]
+                    };
+                    let case = format!("{:?} {}, resized {}", crop_type, option, resize_output);
+
+                    let (memory, streamed) = (dir.join("memory"), dir.join("streamed"));
+                    for out in [&memory, &streamed] {
+                        let _ = std::fs::remove_dir_all(out);
+                        std::fs::create_dir_all(out).unwrap();
+                    }
+                    let (bg_name, file_name) = (FileName::default(), FileName::default());
+                    let saved = autocrop
+                        .crop_files(paths)
+                        .unwrap()
+                        .save(&memory, &bg_name, &file_name)
+                        .unwrap();
+                    let streamed_manifest = autocrop
+                        .crop_files_to(paths, &streamed, &bg_name, &file_name)
+                        .unwrap();
+
+                    assert_eq!(
+                        serde_json::to_string(&saved).unwrap(),
+                        serde_json::to_string(&streamed_manifest).unwrap(),
+                        "{}",
+                        case
+                    );
+                    for entry in &saved.outputs {
+                        assert!(
+                            std::fs::read(memory.join(&entry.output)).unwrap()
+                                == std::fs::read(streamed.join(&entry.output)).unwrap(),
+                            "{} {}",
+                            case,
+                            entry.output.display()
+                        );
+                    }
+                }
+            }
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,119 @@
+//! The error for everything that can go wrong opening, cropping and saving images,
+//! so callers can tell a bad file from a cancelled crop without reading the message.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use image::ImageError;
+
+#[derive(Debug)]
+pub enum CropError {
+    /// An image, or a manifest, couldn't be opened or decoded.
+    Decode { path: PathBuf, source: ImageError },
+    /// The images aren't all the same size and weren't padded, see [`Autocrop::pad`](crate::Autocrop::pad).
+    /// `names` are what each image is called in the message, `sizes` their width and height.
+    SizeMismatch {
+        names: Vec<String>,
+        sizes: Vec<(u32, u32)>,
+    },
+    /// A custom output name that can't be a file name, see [`is_illegal`](crate::is_illegal),
+    /// or the name of an output another output is already saved as.
+    IllegalName { name: String },
+    /// Something couldn't be read from or written to disk.
+    Io { path: PathBuf, source: io::Error },
+    /// The crop was stopped part way, see [`CancelToken`](crate::CancelToken).
+    Cancelled,
+    /// The options or inputs don't go together, like fewer than two images.
+    Invalid(String),
+}
+
+impl CropError {
+    /// The file the error is about, if it's about one.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            CropError::Decode { path, .. } | CropError::Io { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// A decode error for the image at `path`.
+    pub(crate) fn decode(path: &Path, source: ImageError) -> Self {
+        CropError::Decode {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// An io error for the file at `path`.
+    pub(crate) fn io(path: &Path, source: io::Error) -> Self {
+        CropError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for CropError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CropError::Decode { path, source } => {
+                write!(f, "could not open {}: {}", path.display(), source)
+            }
+            CropError::SizeMismatch { names, sizes } => {
+                // grouped by size in the order they first show up, so a single odd one out stands out
+                let mut groups: Vec<((u32, u32), Vec<&str>)> = vec![];
+                for (name, size) in names.iter().zip(sizes) {
+                    match groups.iter_mut().find(|(group_size, _)| group_size == size) {
+                        Some((_, group)) => group.push(name),
+                        None => groups.push((*size, vec![name])),
+                    }
+                }
+
+                write!(
+                    f,
+                    "Images must be the same resolution, or padded onto a common canvas. The sizes are:"
+                )?;
+                for ((width, height), group) in groups {
+                    write!(f, "\n{}x{}: {}", width, height, group.join(", "))?;
+                }
+                Ok(())
+            }
+            CropError::IllegalName { name } => {
+                write!(
+                    f,
+                    "\"{}\" can't be used as a file name, it isn't a valid one or two outputs would share it.",
+                    name
+                )
+            }
+            CropError::Io { path, source } => {
+                write!(f, "could not access {}: {}", path.display(), source)
+            }
+            CropError::Cancelled => write!(f, "The crop was cancelled."),
+            CropError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for CropError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CropError::Decode { source, .. } => Some(source),
+            CropError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for CropError {
+    fn from(message: &str) -> Self {
+        CropError::Invalid(message.to_string())
+    }
+}
+
+impl From<String> for CropError {
+    fn from(message: String) -> Self {
+        CropError::Invalid(message)
+    }
+}
@@ -0,0 +1,172 @@
+//! The json sidecar written next to the cropped images, recording where every output goes back on the canvas.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use image::ImageError;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{AlphaMode, Anchor, CropType, MetricType};
+use crate::crop::{Pos2, Shift};
+use crate::error::CropError;
+use crate::tile::Tile;
+
+/// File name the manifest is saved under, in the output directory.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+/// Bumped whenever the manifest layout changes in a way older readers can't handle.
+/// 2 added sequential crops, where an output can go on top of another rebuilt image.
+/// 3 added aligned crops, where the background is moved before an output goes on top.
+/// 4 added padded crops, where the rebuilt image is cut back down to its original size.
+pub const MANIFEST_VERSION: u32 = 4;
+
+/// Everything needed to place the outputs of a crop back on the background.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    /// Width of the original canvas.
+    pub width: u32,
+    /// Height of the original canvas.
+    pub height: u32,
+    pub crop_type: CropType,
+    /// In the units of the metric.
+    pub leniency: f32,
+    #[serde(default)]
+    pub metric: MetricType,
+    /// Only used by [`MetricType::PerChannel`].
+    #[serde(default)]
+    pub channel_thresholds: [u8; 4],
+    /// Manifests from before alpha was compared used [`AlphaMode::Ignore`].
+    #[serde(default = "ignore_alpha")]
+    pub alpha_mode: AlphaMode,
+    pub resize_output: bool,
+    /// True if every output was cut to its own range rather than one shared range.
+    #[serde(default)]
+    pub per_image_range: bool,
+    /// True if every image was cropped against the one before it, so they have to be rebuilt in order.
+    #[serde(default)]
+    pub sequential: bool,
+    /// Where inputs smaller than the canvas were padded onto it, see [`ManifestEntry::original_width`].
+    #[serde(default)]
+    pub anchor: Anchor,
+    /// Output file name of the background, relative to the manifest.
+    pub background: PathBuf,
+    /// True if the background was made up out of every image rather than being one of them.
+    #[serde(default)]
+    pub synthetic_background: bool,
+    /// Every output written, the background included, in input order.
+    pub outputs: Vec<ManifestEntry>,
+}
+
+/// A single output image.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// The input file this was cropped from, if it came from one.
+    pub source: Option<PathBuf>,
+    /// Output file name, relative to the manifest.
+    pub output: PathBuf,
+    /// Name of the image this output belongs to (without extension), what the reconstruction is saved as.
+    /// Every region of an input shares the same name.
+    #[serde(default)]
+    pub name: String,
+    /// Which region of the input this is, if the input was split into several outputs.
+    #[serde(default)]
+    pub region: Option<usize>,
+    /// Index of the input image.
+    pub index: usize,
+    /// Left edge of the output on the original canvas.
+    pub x: u32,
+    /// Top edge of the output on the original canvas.
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Index of the packed tiles, only for packed tile outputs.
+    /// When present the output is placed tile by tile instead of at `x` and `y`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tiles: Vec<Tile>,
+    /// For sequential crops, index of the input this goes on top of, rebuilt first. `None` means the background.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<usize>,
+    /// For sequential crops, true if this is a whole image that doesn't go on top of anything.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub keyframe: bool,
+    /// How far the background was moved to line up with this input before the output goes on top,
+    /// see [`Autocrop::align`](crate::Autocrop::align).
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub shift_x: i32,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub shift_y: i32,
+    /// Width of the input before it was padded onto the canvas, only set for padded crops.
+    /// The rebuilt image is cut back down to this, placed by the manifest's anchor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_width: Option<u32>,
+    /// Height of the input before it was padded onto the canvas, only set for padded crops.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_height: Option<u32>,
+    pub is_background: bool,
+}
+
+fn ignore_alpha() -> AlphaMode {
+    AlphaMode::Ignore
+}
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+impl ManifestEntry {
+    pub fn offset(&self) -> Pos2 {
+        Pos2::new(self.x, self.y)
+    }
+
+    pub fn shift(&self) -> Shift {
+        Shift::new(self.shift_x, self.shift_y)
+    }
+
+    /// Size of the input before it was padded, `None` if it wasn't.
+    pub fn original_size(&self) -> Option<(u32, u32)> {
+        self.original_width.zip(self.original_height)
+    }
+
+    /// File name the reconstructed image gets.
+    pub fn reconstructed_name(&self) -> PathBuf {
+        if self.name.is_empty() {
+            self.output.clone()
+        } else {
+            PathBuf::from(format!("{}.png", self.name))
+        }
+    }
+}
+
+impl Manifest {
+    /// Writes the manifest as pretty printed json.
+    pub fn save(&self, path: &Path) -> Result<(), CropError> {
+        let writer = BufWriter::new(File::create(path).map_err(|e| CropError::io(path, e))?);
+        serde_json::to_writer_pretty(writer, self).map_err(|e| CropError::io(path, e.into()))?;
+        Ok(())
+    }
+
+    /// Reads a manifest written by [`Manifest::save`].
+    /// A manifest that can't be read is a [`CropError::Io`], one that can't be parsed a [`CropError::Decode`].
+    pub fn load(path: &Path) -> Result<Self, CropError> {
+        let reader = BufReader::new(File::open(path).map_err(|e| CropError::io(path, e))?);
+        let manifest: Manifest = serde_json::from_reader(reader).map_err(|e| {
+            if e.is_io() {
+                CropError::io(path, e.into())
+            } else {
+                CropError::decode(path, ImageError::IoError(e.into()))
+            }
+        })?;
+
+        if manifest.version > MANIFEST_VERSION {
+            return Err(format!(
+                "{} was written by a newer version of autocrop (manifest version {}).",
+                path.display(),
+                manifest.version
+            )
+            .into());
+        }
+
+        Ok(manifest)
+    }
+}
@@ -0,0 +1,121 @@
+//! Cleaning up the mask of different pixels before it's cropped, for noisy (lossy) inputs.
+
+use crate::crop::Pos2;
+
+/// Cleans up the different pixels of one image, in this order:
+/// drops connected groups of less than `min_region_size` pixels (diagonals count as touching),
+/// fills holes up to `close` pixels wide (a closing), then grows everything by `dilate` pixels.
+/// 0 turns each step off.
+///
+/// Returns the pixels left, sorted top to bottom then left to right.
+pub fn cleanup(
+    pixels: &[Pos2],
+    width: u32,
+    height: u32,
+    min_region_size: u32,
+    close: u32,
+    dilate: u32,
+) -> Vec<Pos2> {
+    let (w, h) = (width as usize, height as usize);
+    let mut mask = vec![false; w * h];
+    for Pos2 { x, y } in pixels {
+        mask[*y as usize * w + *x as usize] = true;
+    }
+
+    if min_region_size > 1 {
+        remove_small(&mut mask, w, h, min_region_size as usize);
+    }
+    if close > 0 {
+        mask = grow(&mask, w, h, close as usize);
+        mask = shrink(&mask, w, h, close as usize);
+    }
+    if dilate > 0 {
+        mask = grow(&mask, w, h, dilate as usize);
+    }
+
+    mask.iter()
+        .enumerate()
+        .filter(|(_, set)| **set)
+        .map(|(i, _)| Pos2::new((i % w) as u32, (i / w) as u32))
+        .collect()
+}
+
+/// Clears every connected group smaller than `min` pixels.
+fn remove_small(mask: &mut [bool], w: usize, h: usize, min: usize) {
+    let mut seen = vec![false; w * h];
+    let mut stack = vec![];
+    let mut group = vec![];
+
+    for start in 0..w * h {
+        if !mask[start] || seen[start] {
+            continue;
+        }
+
+        // flood fill out from the starting pixel, remembering the whole group
+        group.clear();
+        seen[start] = true;
+        stack.push(start);
+        while let Some(i) = stack.pop() {
+            group.push(i);
+            let (x, y) = (i % w, i / w);
+            for ny in y.saturating_sub(1)..=(y + 1).min(h - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(w - 1) {
+                    let n = ny * w + nx;
+                    if mask[n] && !seen[n] {
+                        seen[n] = true;
+                        stack.push(n);
+                    }
+                }
+            }
+        }
+
+        if group.len() < min {
+            for i in &group {
+                mask[*i] = false;
+            }
+        }
+    }
+}
+
+/// Sets every pixel within `radius` (a square) of a set pixel.
+fn grow(mask: &[bool], w: usize, h: usize, radius: usize) -> Vec<bool> {
+    // a square is a row pass then a column pass
+    let rows = pass(mask, w, h, radius, true, |count, _| count > 0);
+    pass(&rows, w, h, radius, false, |count, _| count > 0)
+}
+
+/// Keeps only pixels whose whole square of `radius` is set.
+/// Off the edge counts as set, so shrinking right after growing never loses an original pixel.
+fn shrink(mask: &[bool], w: usize, h: usize, radius: usize) -> Vec<bool> {
+    let rows = pass(mask, w, h, radius, true, |count, len| count == len);
+    pass(&rows, w, h, radius, false, |count, len| count == len)
+}
+
+/// Runs over every row (or column), setting each pixel from how many of the `radius` pixels to either side are set.
+/// `keep` gets the count and how many pixels of the window are on the canvas.
+fn pass(
+    mask: &[bool],
+    w: usize,
+    h: usize,
+    radius: usize,
+    rows: bool,
+    keep: impl Fn(usize, usize) -> bool,
+) -> Vec<bool> {
+    let (lines, len) = if rows { (h, w) } else { (w, h) };
+    let index = |line: usize, i: usize| if rows { line * w + i } else { i * w + line };
+
+    let mut new = vec![false; w * h];
+    // running count of set pixels along the line, prefix[i] is the count before i
+    let mut prefix = vec![0; len + 1];
+    for line in 0..lines {
+        for i in 0..len {
+            prefix[i + 1] = prefix[i] + mask[index(line, i)] as usize;
+        }
+        for i in 0..len {
+            let start = i.saturating_sub(radius);
+            let end = (i + radius + 1).min(len);
+            new[index(line, i)] = keep(prefix[end] - prefix[start], end - start);
+        }
+    }
+    new
+}
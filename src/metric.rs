@@ -0,0 +1,401 @@
+//! The ways two pixels can be compared, picked with [`MetricType`](crate::MetricType).
+//!
+//! Every metric has its own units, the leniency is a threshold in those units, see [`Metric::threshold`].
+
+use image::Rgba;
+
+use crate::config::{AlphaMode, MetricType};
+
+/// A way of measuring how different two pixels are. Shared between the threads of a crop.
+pub trait Metric: Send + Sync {
+    /// How different the colors of two pixels are, 0 when they're the same. Alpha is left to [`Metric::difference`].
+    fn color_difference(&self, px1: Rgba<u8>, px2: Rgba<u8>) -> f64;
+
+    /// How different two alpha values are, in the same units as [`Metric::color_difference`].
+    fn alpha_difference(&self, alpha1: u8, alpha2: u8) -> f64;
+
+    /// The difference a pixel has to go over to count as changed, from the leniency the user picked.
+    fn threshold(&self, leniency: f32) -> f64 {
+        leniency as f64
+    }
+
+    /// How different two pixels are, taking transparency into account as set by `alpha_mode`.
+    fn difference(&self, px1: Rgba<u8>, px2: Rgba<u8>, alpha_mode: AlphaMode) -> f64 {
+        match alpha_mode {
+            AlphaMode::Ignore => self.color_difference(px1, px2),
+            // the color of something invisible doesn't matter
+            AlphaMode::Straight if px1[3] == 0 && px2[3] == 0 => 0.0,
+            AlphaMode::Straight => self
+                .color_difference(px1, px2)
+                .max(self.alpha_difference(px1[3], px2[3])),
+            AlphaMode::Premultiplied => self
+                .color_difference(premultiply(px1), premultiply(px2))
+                .max(self.alpha_difference(px1[3], px2[3])),
+        }
+    }
+}
+
+/// Makes the metric for `metric_type`. `channel_thresholds` (RGBA) are only used by [`MetricType::PerChannel`].
+pub fn metric(metric_type: MetricType, channel_thresholds: [u8; 4]) -> Box<dyn Metric> {
+    match metric_type {
+        MetricType::Rgb => Box::new(Rgb),
+        MetricType::LinearRgb => Box::new(LinearRgb::new()),
+        MetricType::MaxChannel => Box::new(MaxChannel),
+        MetricType::PerChannel => Box::new(PerChannel(channel_thresholds)),
+        MetricType::DeltaE76 => Box::new(DeltaE76),
+        MetricType::DeltaE2000 => Box::new(DeltaE2000),
+    }
+}
+
+/// Squared distance in RGB, 0 - 1.0 of the biggest possible distance. The leniency is a percentage of it.
+pub struct Rgb;
+
+impl Metric for Rgb {
+    fn color_difference(&self, px1: Rgba<u8>, px2: Rgba<u8>) -> f64 {
+        let difference = ((px1[0] as i32 - px2[0] as i32).pow(2))
+            + ((px1[1] as i32 - px2[1] as i32).pow(2))
+            + ((px1[2] as i32 - px2[2] as i32).pow(2));
+
+        difference as f64 / 195075.0
+    }
+
+    fn alpha_difference(&self, alpha1: u8, alpha2: u8) -> f64 {
+        (alpha1 as f64 - alpha2 as f64).powi(2) / 65025.0
+    }
+
+    fn threshold(&self, leniency: f32) -> f64 {
+        leniency as f64 / 100.0
+    }
+}
+
+/// Like [`Rgb`], but in linear light instead of gamma encoded sRGB,
+/// so the same change in brightness counts the same in dark and bright areas.
+pub struct LinearRgb {
+    /// sRGB value to linear light, 0 - 1.0.
+    table: [f64; 256],
+}
+
+impl LinearRgb {
+    pub fn new() -> Self {
+        let mut table = [0.0; 256];
+        for (value, linear) in table.iter_mut().enumerate() {
+            *linear = to_linear(value as u8);
+        }
+        Self { table }
+    }
+}
+
+impl Default for LinearRgb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metric for LinearRgb {
+    fn color_difference(&self, px1: Rgba<u8>, px2: Rgba<u8>) -> f64 {
+        (0..3)
+            .map(|c| (self.table[px1[c] as usize] - self.table[px2[c] as usize]).powi(2))
+            .sum::<f64>()
+            / 3.0
+    }
+
+    fn alpha_difference(&self, alpha1: u8, alpha2: u8) -> f64 {
+        // alpha is already linear
+        ((alpha1 as f64 - alpha2 as f64) / 255.0).powi(2)
+    }
+
+    fn threshold(&self, leniency: f32) -> f64 {
+        leniency as f64 / 100.0
+    }
+}
+
+/// The biggest difference of any one channel, 0 - 255.
+pub struct MaxChannel;
+
+impl Metric for MaxChannel {
+    fn color_difference(&self, px1: Rgba<u8>, px2: Rgba<u8>) -> f64 {
+        (0..3)
+            .map(|c| (px1[c] as i32 - px2[c] as i32).abs())
+            .max()
+            .unwrap_or(0) as f64
+    }
+
+    fn alpha_difference(&self, alpha1: u8, alpha2: u8) -> f64 {
+        (alpha1 as f64 - alpha2 as f64).abs()
+    }
+}
+
+/// Every channel has its own threshold (RGBA), a pixel is changed when any channel goes over its own.
+/// The difference is how far past its threshold the worst channel is, so the leniency isn't used.
+pub struct PerChannel(pub [u8; 4]);
+
+impl Metric for PerChannel {
+    fn color_difference(&self, px1: Rgba<u8>, px2: Rgba<u8>) -> f64 {
+        (0..3)
+            .map(|c| (px1[c] as i32 - px2[c] as i32).abs() - self.0[c] as i32)
+            .max()
+            .unwrap_or(0) as f64
+    }
+
+    fn alpha_difference(&self, alpha1: u8, alpha2: u8) -> f64 {
+        ((alpha1 as i32 - alpha2 as i32).abs() - self.0[3] as i32) as f64
+    }
+
+    fn threshold(&self, _leniency: f32) -> f64 {
+        0.0
+    }
+}
+
+/// CIE76 color difference, the straight distance in CIELAB. About 2.3 is the smallest difference most people notice.
+pub struct DeltaE76;
+
+impl Metric for DeltaE76 {
+    fn color_difference(&self, px1: Rgba<u8>, px2: Rgba<u8>) -> f64 {
+        let (lab1, lab2) = (to_lab(px1), to_lab(px2));
+        ((lab1[0] - lab2[0]).powi(2) + (lab1[1] - lab2[1]).powi(2) + (lab1[2] - lab2[2]).powi(2))
+            .sqrt()
+    }
+
+    fn alpha_difference(&self, alpha1: u8, alpha2: u8) -> f64 {
+        alpha_in_lightness(alpha1, alpha2)
+    }
+}
+
+/// CIEDE2000 color difference, CIELAB corrected for how people actually see hue and saturation.
+/// Slower than [`DeltaE76`], but closer to what looks different.
+pub struct DeltaE2000;
+
+impl Metric for DeltaE2000 {
+    fn color_difference(&self, px1: Rgba<u8>, px2: Rgba<u8>) -> f64 {
+        delta_e2000(to_lab(px1), to_lab(px2))
+    }
+
+    fn alpha_difference(&self, alpha1: u8, alpha2: u8) -> f64 {
+        alpha_in_lightness(alpha1, alpha2)
+    }
+}
+
+/// Multiplies the color by the alpha, so what's left is how much the pixel actually adds.
+fn premultiply(px: Rgba<u8>) -> Rgba<u8> {
+    let alpha = px[3] as u32;
+    let scale = |value: u8| ((value as u32 * alpha + 127) / 255) as u8;
+    Rgba([scale(px[0]), scale(px[1]), scale(px[2]), px[3]])
+}
+
+/// Alpha difference on the same 0 - 100 scale as CIELAB lightness, for the delta E metrics.
+fn alpha_in_lightness(alpha1: u8, alpha2: u8) -> f64 {
+    (alpha1 as f64 - alpha2 as f64).abs() / 255.0 * 100.0
+}
+
+/// sRGB value to linear light, 0 - 1.0.
+fn to_linear(value: u8) -> f64 {
+    let value = value as f64 / 255.0;
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// sRGB to CIELAB, with a D65 white point.
+fn to_lab(px: Rgba<u8>) -> [f64; 3] {
+    let (r, g, b) = (to_linear(px[0]), to_linear(px[1]), to_linear(px[2]));
+    // linear sRGB to XYZ, divided by the white point
+    let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / 0.95047;
+    let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+    let z = (0.0193339 * r + 0.1191920 * g + 0.9503041 * b) / 1.08883;
+
+    let f = |t: f64| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// The CIEDE2000 formula, following Sharma, Wu and Dalal's implementation notes.
+fn delta_e2000(lab1: [f64; 3], lab2: [f64; 3]) -> f64 {
+    let [l1, a1, b1] = lab1;
+    let [l2, a2, b2] = lab2;
+    let pow25_7 = 25f64.powi(7);
+
+    // stretch a* so neutral colors get their hue right
+    let c_bar = ((a1 * a1 + b1 * b1).sqrt() + (a2 * a2 + b2 * b2).sqrt()) / 2.0;
+    let g = 0.5 * (1.0 - (c_bar.powi(7) / (c_bar.powi(7) + pow25_7)).sqrt());
+    let (a1, a2) = (a1 * (1.0 + g), a2 * (1.0 + g));
+    let (c1, c2) = ((a1 * a1 + b1 * b1).sqrt(), (a2 * a2 + b2 * b2).sqrt());
+    let hue = |a: f64, b: f64| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.0)
+        }
+    };
+    let (h1, h2) = (hue(a1, b1), hue(a2, b2));
+
+    let delta_l = l2 - l1;
+    let delta_c = c2 - c1;
+    let delta_h = if c1 * c2 == 0.0 {
+        0.0
+    } else if h2 - h1 > 180.0 {
+        h2 - h1 - 360.0
+    } else if h2 - h1 < -180.0 {
+        h2 - h1 + 360.0
+    } else {
+        h2 - h1
+    };
+    let delta_h = 2.0 * (c1 * c2).sqrt() * (delta_h / 2.0).to_radians().sin();
+
+    let l_bar = (l1 + l2) / 2.0;
+    let c_bar = (c1 + c2) / 2.0;
+    let h_bar = if c1 * c2 == 0.0 {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+
+    let cos = |degrees: f64| degrees.to_radians().cos();
+    let t =
+        1.0 - 0.17 * cos(h_bar - 30.0) + 0.24 * cos(2.0 * h_bar) + 0.32 * cos(3.0 * h_bar + 6.0)
+            - 0.20 * cos(4.0 * h_bar - 63.0);
+    let delta_theta = 30.0 * (-((h_bar - 275.0) / 25.0).powi(2)).exp();
+    let r_c = 2.0 * (c_bar.powi(7) / (c_bar.powi(7) + pow25_7)).sqrt();
+    let s_l = 1.0 + 0.015 * (l_bar - 50.0).powi(2) / (20.0 + (l_bar - 50.0).powi(2)).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar;
+    let s_h = 1.0 + 0.015 * c_bar * t;
+    let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+    let (l, c, h) = (delta_l / s_l, delta_c / s_c, delta_h / s_h);
+    (l * l + c * c + h * h + r_t * c * h).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The test pairs from Sharma, Wu and Dalal's CIEDE2000 paper, with the difference they give.
+    const SHARMA_PAIRS: [([f64; 3], [f64; 3], f64); 34] = [
+        ([50.0, 2.6772, -79.7751], [50.0, 0.0, -82.7485], 2.0425),
+        ([50.0, 3.1571, -77.2803], [50.0, 0.0, -82.7485], 2.8615),
+        ([50.0, 2.8361, -74.0200], [50.0, 0.0, -82.7485], 3.4412),
+        ([50.0, -1.3802, -84.2814], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, -1.1848, -84.8006], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, -0.9009, -85.5211], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, 0.0, 0.0], [50.0, -1.0, 2.0], 2.3669),
+        ([50.0, -1.0, 2.0], [50.0, 0.0, 0.0], 2.3669),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0009], 7.1792),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0010], 7.1792),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0011], 7.2195),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0012], 7.2195),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0009, -2.4900], 4.8045),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0010, -2.4900], 4.8045),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0011, -2.4900], 4.7461),
+        ([50.0, 2.5, 0.0], [50.0, 0.0, -2.5], 4.3065),
+        ([50.0, 2.5, 0.0], [73.0, 25.0, -18.0], 27.1492),
+        ([50.0, 2.5, 0.0], [61.0, -5.0, 29.0], 22.8977),
+        ([50.0, 2.5, 0.0], [56.0, -27.0, -3.0], 31.9030),
+        ([50.0, 2.5, 0.0], [58.0, 24.0, 15.0], 19.4535),
+        ([50.0, 2.5, 0.0], [50.0, 3.1736, 0.5854], 1.0000),
+        ([50.0, 2.5, 0.0], [50.0, 3.2972, 0.0], 1.0000),
+        ([50.0, 2.5, 0.0], [50.0, 1.8634, 0.5757], 1.0000),
+        ([50.0, 2.5, 0.0], [50.0, 3.2592, 0.3350], 1.0000),
+        (
+            [60.2574, -34.0099, 36.2677],
+            [60.4626, -34.1751, 39.4387],
+            1.2644,
+        ),
+        (
+            [63.0109, -31.0961, -5.8663],
+            [62.8187, -29.7946, -4.0864],
+            1.2630,
+        ),
+        (
+            [61.2901, 3.7196, -5.3901],
+            [61.4292, 2.2480, -4.9620],
+            1.8731,
+        ),
+        (
+            [35.0831, -44.1164, 3.7933],
+            [35.0232, -40.0716, 1.5901],
+            1.8645,
+        ),
+        (
+            [22.7233, 20.0904, -46.6940],
+            [23.0331, 14.9730, -42.5619],
+            2.0373,
+        ),
+        (
+            [36.4612, 47.8580, 18.3852],
+            [36.2715, 50.5065, 21.2231],
+            1.4146,
+        ),
+        (
+            [90.8027, -2.0831, 1.4410],
+            [91.1528, -1.6435, 0.0447],
+            1.4441,
+        ),
+        (
+            [90.9257, -0.5406, -0.9208],
+            [88.6381, -0.8985, -0.7239],
+            1.5381,
+        ),
+        (
+            [6.7747, -0.2908, -2.4247],
+            [5.8714, -0.0985, -2.2286],
+            0.6377,
+        ),
+        (
+            [2.0776, 0.0795, -1.1350],
+            [0.9033, -0.0636, -0.5514],
+            0.9082,
+        ),
+    ];
+
+    #[test]
+    fn delta_e2000_matches_sharma() {
+        for (i, (lab1, lab2, expected)) in SHARMA_PAIRS.iter().enumerate() {
+            let difference = delta_e2000(*lab1, *lab2);
+            // the paper rounds to 4 decimals
+            assert!(
+                (difference - expected).abs() < 1e-4,
+                "pair {}: {} instead of {}",
+                i + 1,
+                difference,
+                expected
+            );
+            // and it doesn't matter which way round they're compared
+            assert!((delta_e2000(*lab2, *lab1) - difference).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn to_lab_matches_known_colors() {
+        let known = [
+            ([0, 0, 0], [0.0, 0.0, 0.0]),
+            ([255, 255, 255], [100.0, 0.0, 0.0]),
+            ([128, 128, 128], [53.585, 0.0, 0.0]),
+            ([255, 0, 0], [53.241, 80.092, 67.203]),
+            ([0, 255, 0], [87.735, -86.183, 83.179]),
+            ([0, 0, 255], [32.297, 79.188, -107.860]),
+        ];
+        for ([r, g, b], expected) in known {
+            let lab = to_lab(Rgba([r, g, b, 255]));
+            for channel in 0..3 {
+                assert!(
+                    (lab[channel] - expected[channel]).abs() < 0.01,
+                    "{:?} is {:?}, expected {:?}",
+                    [r, g, b],
+                    lab,
+                    expected
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Putting images of different sizes onto one shared canvas so they can be compared, and taking them back off.
+
+use image::{imageops, DynamicImage, GenericImageView, RgbaImage};
+
+use crate::config::Anchor;
+use crate::error::CropError;
+
+/// The smallest canvas every image fits on, the biggest width and height of any of them.
+pub fn canvas_size(images: &[DynamicImage]) -> (u32, u32) {
+    images.iter().fold((0, 0), |(width, height), image| {
+        (width.max(image.width()), height.max(image.height()))
+    })
+}
+
+/// Pads every image with transparency onto a canvas the size of the biggest one, placed by `anchor`.
+/// `None` if they're all the same size already, so nothing needs copying.
+pub fn pad_images(images: &[DynamicImage], anchor: Anchor) -> Option<Vec<DynamicImage>> {
+    let (width, height) = canvas_size(images);
+    if images
+        .iter()
+        .all(|image| image.dimensions() == (width, height))
+    {
+        return None;
+    }
+
+    Some(
+        images
+            .iter()
+            .map(|image| {
+                if image.dimensions() == (width, height) {
+                    image.clone()
+                } else {
+                    pad_image(image, (width, height), anchor)
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Pads a single image with transparency onto a `canvas` sized canvas, placed by `anchor`.
+pub fn pad_image(image: &DynamicImage, canvas: (u32, u32), anchor: Anchor) -> DynamicImage {
+    let mut padded = RgbaImage::new(canvas.0, canvas.1);
+    let offset = anchor.offset(image.dimensions(), canvas);
+    imageops::replace(&mut padded, &image.to_rgba8(), offset.x, offset.y);
+    DynamicImage::ImageRgba8(padded)
+}
+
+/// Cuts an image padded by [`pad_images`] back down to its original `size`.
+pub fn unpad(image: &RgbaImage, size: (u32, u32), anchor: Anchor) -> RgbaImage {
+    let offset = anchor.offset(size, image.dimensions());
+    imageops::crop_imm(image, offset.x, offset.y, size.0, size.1).to_image()
+}
+
+/// The error for images that aren't all the same size, listing every size and which images have it,
+/// `None` if they're all the same. `names` are what each image is called in the message.
+pub fn size_error(names: &[String], sizes: &[(u32, u32)]) -> Option<CropError> {
+    if sizes.windows(2).all(|pair| pair[0] == pair[1]) {
+        return None;
+    }
+    Some(CropError::SizeMismatch {
+        names: names.to_vec(),
+        sizes: sizes.to_vec(),
+    })
+}
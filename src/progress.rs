@@ -0,0 +1,163 @@
+//! Reporting how far along a crop is, and stopping it part way, see [`Autocrop::progress`](crate::Autocrop::progress).
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::error::CropError;
+
+/// What a crop is busy with. A crop goes through them in this order, skipping the ones it doesn't need.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    /// Opening every image up front, for crops that need them all at once.
+    Opening,
+    /// Trying images as the background, for [`BackgroundType::Best`](crate::BackgroundType::Best).
+    /// Steps are candidates rather than every image.
+    Background,
+    /// Comparing every image against the background to find what changed.
+    Comparing,
+    /// Cutting the changed pixels out of every image.
+    Cropping,
+    /// Writing the outputs to disk. Crops that save as they go cut and save in this phase.
+    Saving,
+}
+
+impl Phase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Opening => "opening",
+            Phase::Background => "picking background",
+            Phase::Comparing => "comparing",
+            Phase::Cropping => "cropping",
+            Phase::Saving => "saving",
+        }
+    }
+}
+
+/// One step of a crop, reported every time an image gets through a phase.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Update {
+    pub phase: Phase,
+    /// Index of the image that just got through the phase, images can finish out of order.
+    pub index: usize,
+    /// How many images are through the phase so far, including this one.
+    pub done: usize,
+    /// How many images the phase goes through.
+    pub total: usize,
+    /// Bytes of output written to disk so far, over every phase.
+    pub bytes_written: u64,
+}
+
+/// Stops a crop part way when cancelled. Clones share the same token, so one can be kept to cancel with.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks the crop to stop, it does at the next image.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Called with every line passed to [`Progress::messages`].
+type MessageCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Where a crop reports its progress, and the token that cancels it.
+/// The default reports nowhere and is never cancelled.
+#[derive(Clone, Default)]
+pub struct Progress {
+    callback: Option<Arc<dyn Fn(Update) + Send + Sync>>,
+    messages: Option<MessageCallback>,
+    cancel: CancelToken,
+    /// Images through the current phase, shared by every thread working on it.
+    done: Arc<AtomicUsize>,
+    bytes_written: Arc<AtomicU64>,
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Progress")
+            .field("callback", &self.callback.is_some())
+            .field("messages", &self.messages.is_some())
+            .field("cancel", &self.cancel)
+            .field("done", &self.done)
+            .field("bytes_written", &self.bytes_written)
+            .finish()
+    }
+}
+
+impl Progress {
+    /// Calls `callback` with every update, from whichever thread the image was worked on.
+    pub fn new(callback: impl Fn(Update) + Send + Sync + 'static) -> Self {
+        Self {
+            callback: Some(Arc::new(callback)),
+            ..Self::default()
+        }
+    }
+
+    /// Also calls `callback` with a line about what the crop is doing that isn't counted in images,
+    /// like padding them or making the background, for logging.
+    pub fn messages(mut self, callback: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.messages = Some(Arc::new(callback));
+        self
+    }
+
+    /// Stops the crop when `cancel` is cancelled.
+    pub fn cancel_token(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Starts a phase, reported with nothing done yet.
+    pub(crate) fn start(&self, phase: Phase, total: usize) {
+        self.done.store(0, Ordering::Relaxed);
+        self.report(phase, 0, 0, total);
+    }
+
+    /// Reports that image `index` got through the phase.
+    pub(crate) fn step(&self, phase: Phase, index: usize, total: usize) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        self.report(phase, index, done, total);
+    }
+
+    /// Counts `bytes` more written to disk, reported with the next step.
+    pub(crate) fn wrote(&self, bytes: u64) {
+        self.bytes_written.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Passes `message` on to the messages callback, if there is one.
+    pub(crate) fn message(&self, message: &str) {
+        if let Some(messages) = &self.messages {
+            messages(message);
+        }
+    }
+
+    /// An error if the crop was cancelled, checked before every image.
+    pub(crate) fn check(&self) -> Result<(), CropError> {
+        if self.cancel.is_cancelled() {
+            Err(CropError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn report(&self, phase: Phase, index: usize, done: usize, total: usize) {
+        if let Some(callback) = &self.callback {
+            callback(Update {
+                phase,
+                index,
+                done,
+                total,
+                bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            });
+        }
+    }
+}
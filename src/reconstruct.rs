@@ -0,0 +1,718 @@
+//! Reverses a crop, putting the cropped images back on top of the background to rebuild the originals.
+
+use std::path::{Path, PathBuf};
+
+use image::{GenericImageView, Rgba, RgbaImage};
+
+use crate::crop::{save_png, CropResult, Pos2, Shift};
+use crate::error::CropError;
+use crate::manifest::Manifest;
+use crate::tile::Tile;
+
+/// Rebuilds an original image by placing `cropped` on top of `background` at `offset`.
+///
+/// Every pixel of the cropped image that isn't fully transparent black replaces the background pixel,
+/// it isn't blended, since the kept pixel *is* the original pixel.
+/// Fully transparent black pixels are what the crop threw away, so the background shows through them.
+/// A kept pixel that was clear is saved as [`CLEARED`](crate::crop::CLEARED) so it still replaces the background.
+pub fn reconstruct(
+    background: &RgbaImage,
+    cropped: &RgbaImage,
+    offset: Pos2,
+) -> Result<RgbaImage, CropError> {
+    let mut image = background.clone();
+    place(&mut image, cropped, offset)?;
+    Ok(image)
+}
+
+/// Places a cropped image onto the canvas in place, see [`reconstruct`].
+/// Used directly when an input was split into several outputs.
+pub fn place(canvas: &mut RgbaImage, cropped: &RgbaImage, offset: Pos2) -> Result<(), CropError> {
+    if offset.x as u64 + cropped.width() as u64 > canvas.width() as u64
+        || offset.y as u64 + cropped.height() as u64 > canvas.height() as u64
+    {
+        return Err(format!(
+            "A {}x{} image at {}, {} does not fit on the {}x{} background.",
+            cropped.width(),
+            cropped.height(),
+            offset.x,
+            offset.y,
+            canvas.width(),
+            canvas.height()
+        )
+        .into());
+    }
+
+    for (x, y, px) in cropped.enumerate_pixels() {
+        if *px != Rgba([0, 0, 0, 0]) {
+            canvas.put_pixel(offset.x + x, offset.y + y, *px);
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies packed tiles back to where they were taken from on the canvas.
+/// Tiles are copied whole, transparent pixels included, since every pixel of a kept tile is from the original.
+pub fn place_tiles(
+    canvas: &mut RgbaImage,
+    packed: &RgbaImage,
+    tiles: &[Tile],
+) -> Result<(), CropError> {
+    for tile in tiles {
+        let fits = |x: u32, y: u32, image: &RgbaImage| {
+            x as u64 + tile.width as u64 <= image.width() as u64
+                && y as u64 + tile.height as u64 <= image.height() as u64
+        };
+        if !fits(tile.x, tile.y, canvas) || !fits(tile.packed_x, tile.packed_y, packed) {
+            return Err(format!(
+                "The {}x{} tile at {}, {} does not fit on the canvas or packed image.",
+                tile.width, tile.height, tile.x, tile.y
+            )
+            .into());
+        }
+
+        for y in 0..tile.height {
+            for x in 0..tile.width {
+                let px = *packed.get_pixel(tile.packed_x + x, tile.packed_y + y);
+                canvas.put_pixel(tile.x + x, tile.y + y, px);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Moves the image by `shift`, see [`Shift`]. Whatever the moved image doesn't reach is left fully transparent,
+/// an aligned crop always keeps those pixels so they get covered.
+pub fn shift_image(image: &RgbaImage, shift: Shift) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    RgbaImage::from_fn(width, height, |x, y| {
+        crate::align::shifted_pixel(image, shift, x, y).unwrap_or(Rgba([0, 0, 0, 0]))
+    })
+}
+
+impl CropResult {
+    /// Rebuilds every original image in memory, indexed the same as the inputs.
+    /// Sequential crops are rebuilt in order, each on top of the image it was cropped against.
+    pub fn reconstruct(&self) -> Result<Vec<RgbaImage>, CropError> {
+        let background = self
+            .outputs
+            .iter()
+            .find(|output| output.is_background)
+            .ok_or("The crop has no background image.")?;
+
+        let mut images = vec![background.image.clone(); self.inputs];
+        let mut started = vec![false; self.inputs];
+        for output in self.outputs.iter().filter(|output| !output.is_background) {
+            // the first piece of an image decides what it starts from
+            if !started[output.index] {
+                started[output.index] = true;
+                if output.keyframe {
+                    images[output.index] = output.image.clone();
+                    continue;
+                }
+                if let Some(base) = output.base {
+                    images[output.index] = images[base].clone();
+                } else if output.shift != Shift::default() {
+                    images[output.index] = shift_image(&background.image, output.shift);
+                }
+            }
+
+            if output.tiles.is_empty() {
+                place(&mut images[output.index], &output.image, output.offset)?;
+            } else {
+                place_tiles(&mut images[output.index], &output.image, &output.tiles)?;
+            }
+        }
+
+        // padded inputs are cut back down once everything is on, sequential crops needed them whole until now
+        for output in &self.outputs {
+            if let (Some(size), Some(image)) = (output.original_size, images.get_mut(output.index))
+            {
+                if image.dimensions() != size {
+                    *image = crate::pad::unpad(image, size, self.anchor);
+                }
+            }
+        }
+
+        Ok(images)
+    }
+}
+
+/// Rebuilds the originals from a saved background and saved cropped images, writing them to `output_dir`
+/// under the same file names as the cropped images. Returns the paths written.
+///
+/// Cropped images the same size as the background go on at (0, 0),
+/// smaller (resized) ones need the `offset` they were cropped at.
+pub fn reconstruct_files(
+    background: &Path,
+    cropped: &[PathBuf],
+    offset: Option<Pos2>,
+    output_dir: &Path,
+) -> Result<Vec<PathBuf>, CropError> {
+    let bg = image::open(background)
+        .map_err(|e| CropError::decode(background, e))?
+        .to_rgba8();
+
+    let mut written = vec![];
+    for path in cropped {
+        let piece = image::open(path)
+            .map_err(|e| CropError::decode(path, e))?
+            .to_rgba8();
+
+        let piece_offset = if piece.dimensions() == bg.dimensions() {
+            Pos2::default()
+        } else {
+            offset.ok_or_else(|| {
+                format!(
+                    "{} is smaller than the background, the offset it was cropped at is needed.",
+                    path.display()
+                )
+            })?
+        };
+
+        let file_name = path
+            .file_name()
+            .ok_or("Could not retrieve original file name.")?;
+        let out = output_dir.join(file_name);
+        // writing over the cropped image would lose it if anything went wrong
+        if out == *path {
+            return Err(format!(
+                "The output directory would overwrite {}, pick a different one.",
+                path.display()
+            )
+            .into());
+        }
+
+        let image = reconstruct(&bg, &piece, piece_offset)?;
+        save_png(&image, &out)?;
+        written.push(out);
+    }
+
+    Ok(written)
+}
+
+/// Rebuilds the originals listed in a saved [`Manifest`], writing them to `output_dir`
+/// under the same file names as the cropped images. Returns the paths written.
+///
+/// The offsets come from the manifest, so resized outputs don't need anything else.
+/// The background is already whole and isn't written again, unless it was padded onto the canvas
+/// and needs cutting back down to its own size.
+pub fn reconstruct_manifest(
+    manifest_path: &Path,
+    output_dir: &Path,
+) -> Result<Vec<PathBuf>, CropError> {
+    let manifest = Manifest::load(manifest_path)?;
+    // everything in the manifest is relative to the folder it's in
+    let dir = manifest_path.parent().unwrap_or_else(|| Path::new(""));
+
+    let background = dir.join(&manifest.background);
+    let bg = image::open(&background)
+        .map_err(|e| CropError::decode(&background, e))?
+        .to_rgba8();
+    if bg.dimensions() != (manifest.width, manifest.height) {
+        return Err(format!(
+            "{} is {}x{}, but the manifest expects {}x{}.",
+            background.display(),
+            bg.width(),
+            bg.height(),
+            manifest.width,
+            manifest.height
+        )
+        .into());
+    }
+
+    let bg_index = manifest
+        .outputs
+        .iter()
+        .find(|entry| entry.is_background)
+        .map(|entry| entry.index);
+
+    // every piece of an input goes onto the same canvas, grouped by input index
+    let mut images: Vec<(PathBuf, RgbaImage)> = vec![];
+    let mut indices: Vec<usize> = vec![];
+    for entry in manifest.outputs.iter().filter(|entry| !entry.is_background) {
+        let path = dir.join(&entry.output);
+        let piece = image::open(&path)
+            .map_err(|e| CropError::decode(&path, e))?
+            .to_rgba8();
+
+        let canvas = match indices.iter().position(|index| *index == entry.index) {
+            Some(i) => &mut images[i].1,
+            None => {
+                // sequential crops start from whatever they were cropped against, which was rebuilt earlier
+                let start = if entry.keyframe {
+                    if piece.dimensions() != bg.dimensions() {
+                        return Err(format!(
+                            "The keyframe {} is {}x{}, but the manifest expects {}x{}.",
+                            path.display(),
+                            piece.width(),
+                            piece.height(),
+                            manifest.width,
+                            manifest.height
+                        )
+                        .into());
+                    }
+                    piece.clone()
+                } else {
+                    match entry.base {
+                        Some(base) if Some(base) != bg_index => {
+                            match indices.iter().position(|index| *index == base) {
+                                Some(i) => images[i].1.clone(),
+                                None => {
+                                    return Err(format!(
+                                        "{} goes on top of image {}, which doesn't come before it in the manifest.",
+                                        path.display(),
+                                        base
+                                    )
+                                    .into())
+                                }
+                            }
+                        }
+                        _ if entry.shift() != Shift::default() => shift_image(&bg, entry.shift()),
+                        _ => bg.clone(),
+                    }
+                };
+                indices.push(entry.index);
+                images.push((entry.reconstructed_name(), start));
+                &mut images.last_mut().unwrap().1
+            }
+        };
+
+        if entry.keyframe {
+            // already the whole image
+            continue;
+        }
+        if entry.tiles.is_empty() {
+            place(canvas, &piece, entry.offset())?;
+        } else {
+            place_tiles(canvas, &piece, &entry.tiles)?;
+        }
+    }
+
+    // a padded background was saved on the canvas, so it's only rebuilt to get its own size back
+    if let Some(entry) = manifest.outputs.iter().find(|entry| {
+        entry.is_background
+            && matches!(entry.original_size(), Some(size) if size != bg.dimensions())
+    }) {
+        indices.push(entry.index);
+        images.push((entry.reconstructed_name(), bg));
+    }
+
+    let mut written = vec![];
+    for ((name, image), index) in images.into_iter().zip(indices) {
+        let out = output_dir.join(&name);
+        // writing over a cropped image would lose it if anything went wrong
+        if manifest
+            .outputs
+            .iter()
+            .any(|entry| dir.join(&entry.output) == out)
+        {
+            return Err(format!(
+                "The output directory would overwrite {}, pick a different one.",
+                out.display()
+            )
+            .into());
+        }
+
+        // padded inputs are cut back down, sequential crops needed them whole until now
+        let original_size = manifest
+            .outputs
+            .iter()
+            .find(|entry| entry.index == index)
+            .and_then(|entry| entry.original_size());
+        match original_size {
+            Some(size) if size != image.dimensions() => {
+                save_png(&crate::pad::unpad(&image, size, manifest.anchor), &out)?
+            }
+            _ => save_png(&image, &out)?,
+        }
+        written.push(out);
+    }
+
+    Ok(written)
+}
+
+/// Checks that a crop round trips, comparing the rebuilt images against the inputs pixel for pixel.
+/// Returns the index of the first input that doesn't match, only expect a perfect match with a leniency of 0.
+///
+/// Fully transparent pixels match whatever their color, since it can't be seen,
+/// which is also all [`AlphaMode::Straight`](crate::AlphaMode::Straight) promises to keep.
+/// [`AlphaMode::Ignore`](crate::AlphaMode::Ignore) can miss changes to alpha,
+/// and [`AlphaMode::Premultiplied`](crate::AlphaMode::Premultiplied) small changes to faint colors, so they can fail this.
+pub fn verify<I: GenericImageView<Pixel = Rgba<u8>>>(
+    result: &CropResult,
+    originals: &[I],
+) -> Result<Option<usize>, CropError> {
+    let rebuilt = result.reconstruct()?;
+    if rebuilt.len() > originals.len() {
+        return Err("There are less originals than cropped images.".into());
+    }
+
+    for (i, (image, original)) in rebuilt.iter().zip(originals).enumerate() {
+        if original.dimensions() != image.dimensions()
+            || image
+                .enumerate_pixels()
+                .any(|(x, y, px)| !same_pixel(original.get_pixel(x, y), *px))
+        {
+            return Ok(Some(i));
+        }
+    }
+    Ok(None)
+}
+
+/// Exact match, except two fully transparent pixels always match.
+fn same_pixel(px1: Rgba<u8>, px2: Rgba<u8>) -> bool {
+    px1 == px2 || (px1[3] == 0 && px2[3] == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Anchor, BackgroundType, CropType, FileName};
+    use crate::crop::Autocrop;
+    use crate::manifest::MANIFEST_FILE_NAME;
+    use image::DynamicImage;
+
+    const CROP_TYPES: [CropType; 4] = [
+        CropType::Rectangle,
+        CropType::Exact,
+        CropType::Regions,
+        CropType::Tiles,
+    ];
+
+    /// A textured background, then the same with a block changed, and with two blocks far apart
+    /// (so regions splits it) and a pixel made clear.
+    fn images() -> Vec<DynamicImage> {
+        let bg = RgbaImage::from_fn(40, 24, |x, y| {
+            Rgba([(x * 7) as u8, (y * 11) as u8, ((x + y) * 3) as u8, 255])
+        });
+        let mut one = bg.clone();
+        let mut two = bg.clone();
+        for y in 4..9 {
+            for x in 3..8 {
+                one.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+                two.put_pixel(x, y, Rgba([0, 0, 255, 128]));
+                two.put_pixel(x + 28, y + 12, Rgba([255, 0, 0, 255]));
+            }
+        }
+        two.put_pixel(20, 12, Rgba([0, 0, 0, 0]));
+
+        vec![bg, one, two]
+            .into_iter()
+            .map(DynamicImage::ImageRgba8)
+            .collect()
+    }
+
+    #[test]
+    fn every_crop_type_round_trips() {
+        let images = images();
+        for crop_type in CROP_TYPES {
+            for resize_output in [false, true] {
+                let result = Autocrop::new()
+                    .crop_type(crop_type)
+                    .resize_output(resize_output)
+                    .tile_size(4)
+                    .crop(&images)
+                    .unwrap();
+                assert_eq!(
+                    verify(&result, &images).unwrap(),
+                    None,
+                    "{:?}, resized {}",
+                    crop_type,
+                    resize_output
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn manifest_round_trips() {
+        let dir =
+            std::env::temp_dir().join(format!("autocrop-manifest-test-{}", std::process::id()));
+        let cropped = dir.join("cropped");
+        let rebuilt = dir.join("rebuilt");
+        std::fs::create_dir_all(&cropped).unwrap();
+        std::fs::create_dir_all(&rebuilt).unwrap();
+
+        let images = images();
+        let paths: Vec<PathBuf> = (0..images.len())
+            .map(|i| PathBuf::from(format!("image{}.png", i)))
+            .collect();
+        let manifest = Autocrop::new()
+            .crop_type(CropType::Regions)
+            .resize_output(true)
+            .crop(&images)
+            .unwrap()
+            .with_sources(&paths)
+            .save(&cropped, &FileName::default(), &FileName::default())
+            .unwrap();
+
+        let manifest_path = cropped.join(MANIFEST_FILE_NAME);
+        let loaded = Manifest::load(&manifest_path).unwrap();
+        assert_eq!(
+            serde_json::to_string(&loaded).unwrap(),
+            serde_json::to_string(&manifest).unwrap()
+        );
+
+        let written = reconstruct_manifest(&manifest_path, &rebuilt).unwrap();
+        // the background isn't rebuilt, it's already whole
+        assert_eq!(written.len(), images.len() - 1);
+        for path in &written {
+            let i = paths
+                .iter()
+                .position(|original| Some(original.as_os_str()) == path.file_name())
+                .unwrap();
+            let image = image::open(path).unwrap().to_rgba8();
+            assert_eq!(image.dimensions(), images[i].dimensions());
+            assert!(image
+                .enumerate_pixels()
+                .all(|(x, y, px)| same_pixel(images[i].get_pixel(x, y), *px)));
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Crops `images`, saves them and rebuilds them from the manifest, checking every input comes back
+    /// at its own size with the same pixels. Only a background that wasn't padded isn't rebuilt, it's already whole.
+    fn round_trips_on_disk(autocrop: Autocrop, images: &[DynamicImage], case: &str) -> Manifest {
+        let dir = std::env::temp_dir().join(format!(
+            "autocrop-{}-{}",
+            case.replace(|c: char| !c.is_ascii_alphanumeric(), "-"),
+            std::process::id()
+        ));
+        let cropped = dir.join("cropped");
+        let rebuilt = dir.join("rebuilt");
+        std::fs::create_dir_all(&cropped).unwrap();
+        std::fs::create_dir_all(&rebuilt).unwrap();
+
+        let paths: Vec<PathBuf> = (0..images.len())
+            .map(|i| PathBuf::from(format!("image{}.png", i)))
+            .collect();
+        let manifest = autocrop
+            .crop(images)
+            .unwrap()
+            .with_sources(&paths)
+            .save(&cropped, &FileName::default(), &FileName::default())
+            .unwrap();
+
+        let written = reconstruct_manifest(&cropped.join(MANIFEST_FILE_NAME), &rebuilt).unwrap();
+        let mut rebuilt_inputs = vec![];
+        for path in &written {
+            let i = paths
+                .iter()
+                .position(|original| Some(original.as_os_str()) == path.file_name())
+                .unwrap();
+            let image = image::open(path).unwrap().to_rgba8();
+            assert_eq!(
+                image.dimensions(),
+                images[i].dimensions(),
+                "{} image {}",
+                case,
+                i
+            );
+            assert!(
+                image
+                    .enumerate_pixels()
+                    .all(|(x, y, px)| same_pixel(images[i].get_pixel(x, y), *px)),
+                "{} image {}",
+                case,
+                i
+            );
+            rebuilt_inputs.push(i);
+        }
+        let whole = manifest
+            .outputs
+            .iter()
+            .find(|entry| entry.is_background && entry.original_width.is_none())
+            .map(|entry| entry.index);
+        for i in 0..images.len() {
+            assert!(
+                rebuilt_inputs.contains(&i) || whole == Some(i),
+                "{} image {} wasn't rebuilt",
+                case,
+                i
+            );
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        manifest
+    }
+
+    #[test]
+    fn sequence_with_a_keyframe_round_trips() {
+        let mut frames = images();
+        // every color turned around, too much changed to go on top of the one before
+        let mut inverted = frames[2].to_rgba8();
+        for px in inverted.pixels_mut() {
+            for c in 0..3 {
+                px[c] = 255 - px[c];
+            }
+        }
+        frames.push(DynamicImage::ImageRgba8(inverted.clone()));
+        // then a small change again, which goes on top of the keyframe
+        for y in 14..18 {
+            for x in 10..14 {
+                inverted.put_pixel(x, y, Rgba([0, 255, 0, 255]));
+            }
+        }
+        frames.push(DynamicImage::ImageRgba8(inverted));
+
+        for crop_type in CROP_TYPES {
+            for resize_output in [false, true] {
+                let case = format!("sequence {:?} resized {}", crop_type, resize_output);
+                let manifest = round_trips_on_disk(
+                    Autocrop::new()
+                        .crop_type(crop_type)
+                        .resize_output(resize_output)
+                        .tile_size(4)
+                        .sequential(true)
+                        // the rectangle around the two blocks in image 2 is over half of it
+                        .keyframe_ratio(0.9),
+                    &frames,
+                    &case,
+                );
+
+                assert!(manifest.sequential);
+                for entry in manifest.outputs.iter().filter(|entry| !entry.is_background) {
+                    // every image goes on top of the one before, except the keyframe
+                    if entry.index == 3 {
+                        assert!(entry.keyframe, "{}", case);
+                        assert_eq!(entry.base, None, "{}", case);
+                    } else {
+                        assert!(!entry.keyframe, "{} image {}", case, entry.index);
+                        assert_eq!(entry.base, Some(entry.index - 1), "{}", case);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn padded_images_round_trip_at_every_anchor() {
+        let images = images();
+        // a smaller background, so it's padded too, and a smaller image with its change in a corner
+        let sizes = [(34, 18), (40, 24), (40, 24), (30, 20)];
+        let padded: Vec<DynamicImage> = vec![
+            images[0].crop_imm(2, 3, 34, 18),
+            images[1].clone(),
+            images[2].clone(),
+            images[2].crop_imm(10, 4, 30, 20),
+        ];
+
+        for anchor in [
+            Anchor::TopLeft,
+            Anchor::Top,
+            Anchor::TopRight,
+            Anchor::Left,
+            Anchor::Center,
+            Anchor::Right,
+            Anchor::BottomLeft,
+            Anchor::Bottom,
+            Anchor::BottomRight,
+        ] {
+            for crop_type in CROP_TYPES {
+                let case = format!("padded {:?} {:?}", anchor, crop_type);
+                let manifest = round_trips_on_disk(
+                    Autocrop::new()
+                        .crop_type(crop_type)
+                        .resize_output(true)
+                        .tile_size(4)
+                        .pad(true)
+                        .anchor(anchor),
+                    &padded,
+                    &case,
+                );
+
+                assert_eq!((manifest.width, manifest.height), (40, 24), "{}", case);
+                for entry in &manifest.outputs {
+                    assert_eq!(
+                        entry.original_width.zip(entry.original_height),
+                        Some(sizes[entry.index]),
+                        "{} image {}",
+                        case,
+                        entry.index
+                    );
+                }
+            }
+        }
+    }
+
+    /// The plain background, then with a block changed on either side of it, the plain one in the middle.
+    fn either_side() -> (RgbaImage, Vec<DynamicImage>) {
+        let plain = images()[0].to_rgba8();
+        let (mut left, mut right) = (plain.clone(), plain.clone());
+        for y in 8..16 {
+            for x in 2..10 {
+                left.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+                right.put_pixel(x + 28, y, Rgba([0, 0, 0, 255]));
+            }
+        }
+        let images = vec![left, plain.clone(), right]
+            .into_iter()
+            .map(DynamicImage::ImageRgba8)
+            .collect();
+        (plain, images)
+    }
+
+    #[test]
+    fn best_background_round_trips() {
+        let (_, images) = either_side();
+        for crop_type in CROP_TYPES {
+            let case = format!("best {:?}", crop_type);
+            let manifest = round_trips_on_disk(
+                Autocrop::new()
+                    .crop_type(crop_type)
+                    .resize_output(true)
+                    .per_image_range(true)
+                    .tile_size(4)
+                    .background_type(BackgroundType::Best),
+                &images,
+                &case,
+            );
+            // the plain one only has to keep one block for each of the others
+            assert!(!manifest.synthetic_background, "{}", case);
+            assert_eq!(manifest.background, PathBuf::from("image1.png"), "{}", case);
+        }
+    }
+
+    #[test]
+    fn made_up_backgrounds_round_trip() {
+        let (plain, images) = either_side();
+        for background_type in [BackgroundType::Median, BackgroundType::Mode] {
+            // every block is only in one image, so both come out as the plain one
+            let result = Autocrop::new()
+                .background_type(background_type)
+                .crop(&images)
+                .unwrap();
+            let background = result
+                .outputs
+                .iter()
+                .find(|output| output.is_background)
+                .unwrap();
+            assert_eq!(background.index, images.len());
+            assert!(background.image == plain, "{:?}", background_type);
+
+            for crop_type in CROP_TYPES {
+                let case = format!("{:?} {:?}", background_type, crop_type);
+                let manifest = round_trips_on_disk(
+                    Autocrop::new()
+                        .crop_type(crop_type)
+                        .resize_output(true)
+                        .tile_size(4)
+                        .background_type(background_type),
+                    &images,
+                    &case,
+                );
+                assert!(manifest.synthetic_background, "{}", case);
+                assert_eq!(
+                    manifest.background,
+                    PathBuf::from("background.png"),
+                    "{}",
+                    case
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Splitting a set of different pixels into separate regions, used by [`CropType::Regions`](crate::CropType::Regions).
+
+use crate::crop::{Pos2, RectangleRange};
+
+/// Groups the different pixels into connected clusters (diagonals count as touching),
+/// then merges any clusters whose boxes are within `merge_distance` pixels of eachother.
+///
+/// Returns the bounding box of every region, sorted top to bottom then left to right.
+pub fn find_regions(
+    pixels: &[Pos2],
+    width: u32,
+    height: u32,
+    merge_distance: u32,
+) -> Vec<RectangleRange> {
+    // mask of pixels that haven't been put in a region yet
+    let mut mask = vec![false; width as usize * height as usize];
+    for Pos2 { x, y } in pixels {
+        mask[*y as usize * width as usize + *x as usize] = true;
+    }
+
+    let mut regions = vec![];
+    let mut stack = vec![];
+    for start in pixels {
+        if !mask[start.y as usize * width as usize + start.x as usize] {
+            continue;
+        }
+
+        // flood fill out from the starting pixel, clearing the mask as we go
+        let mut range = RectangleRange::new();
+        mask[start.y as usize * width as usize + start.x as usize] = false;
+        stack.push(*start);
+        while let Some(Pos2 { x, y }) = stack.pop() {
+            range.correct(x, y);
+
+            for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                    let i = ny as usize * width as usize + nx as usize;
+                    if mask[i] {
+                        mask[i] = false;
+                        stack.push(Pos2::new(nx, ny));
+                    }
+                }
+            }
+        }
+        regions.push(range);
+    }
+
+    let mut regions = merge_regions(regions, merge_distance);
+    regions.sort_by_key(|range| (range.min.y, range.min.x));
+    regions
+}
+
+/// Keeps merging boxes that are close enough to eachother until none are left.
+/// Boxes that overlap are always merged.
+fn merge_regions(mut regions: Vec<RectangleRange>, merge_distance: u32) -> Vec<RectangleRange> {
+    loop {
+        let mut merged = false;
+
+        let mut i = 0;
+        while i < regions.len() {
+            let mut j = i + 1;
+            while j < regions.len() {
+                if regions[i].near(&regions[j], merge_distance) {
+                    let other = regions.swap_remove(j);
+                    regions[i].union(&other);
+                    merged = true;
+                } else {
+                    j += 1;
+                }
+            }
+            i += 1;
+        }
+
+        // a merge can grow a box into range of one that was already checked, so go again
+        if !merged {
+            return regions;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(x1: u32, y1: u32, x2: u32, y2: u32) -> RectangleRange {
+        let mut range = RectangleRange::new();
+        range.correct(x1, y1);
+        range.correct(x2, y2);
+        range
+    }
+
+    fn corners(regions: &[RectangleRange]) -> Vec<(u32, u32, u32, u32)> {
+        let mut corners: Vec<_> = regions
+            .iter()
+            .map(|range| (range.min.x, range.min.y, range.max.x, range.max.y))
+            .collect();
+        corners.sort_unstable();
+        corners
+    }
+
+    #[test]
+    fn merges_boxes_within_the_distance() {
+        // side by side with no gap, merged even at 0
+        let adjacent = vec![range(0, 0, 3, 3), range(4, 0, 7, 3)];
+        assert_eq!(corners(&merge_regions(adjacent, 0)), [(0, 0, 7, 3)]);
+
+        let overlapping = vec![range(0, 0, 5, 5), range(3, 2, 9, 4)];
+        assert_eq!(corners(&merge_regions(overlapping, 0)), [(0, 0, 9, 5)]);
+
+        // 4 pixels between them
+        let apart = || vec![range(0, 0, 3, 3), range(8, 0, 11, 3)];
+        assert_eq!(corners(&merge_regions(apart(), 4)), [(0, 0, 11, 3)]);
+        assert_eq!(
+            corners(&merge_regions(apart(), 3)),
+            [(0, 0, 3, 3), (8, 0, 11, 3)]
+        );
+
+        // close on one axis but far on the other
+        let diagonal = vec![range(0, 0, 3, 3), range(5, 20, 8, 23)];
+        assert_eq!(corners(&merge_regions(diagonal, 4)).len(), 2);
+    }
+
+    #[test]
+    fn merges_boxes_that_grew_into_range() {
+        // the first box isn't near either of the others, only the box they make once they've merged
+        let regions = vec![range(0, 10, 1, 11), range(4, 0, 5, 1), range(7, 3, 20, 20)];
+        assert_eq!(
+            corners(&merge_regions(regions.clone(), 2)),
+            [(0, 0, 20, 20)]
+        );
+        assert_eq!(corners(&merge_regions(regions[..2].to_vec(), 2)).len(), 2);
+    }
+
+    #[test]
+    fn labels_touching_pixels_as_one_region() {
+        let pixels = [
+            // a diagonal line, touching only at the corners
+            Pos2::new(1, 1),
+            Pos2::new(2, 2),
+            Pos2::new(3, 3),
+            // a separate pair further down
+            Pos2::new(10, 8),
+            Pos2::new(11, 8),
+            // and one above it on the right
+            Pos2::new(14, 0),
+        ];
+        let regions = find_regions(&pixels, 16, 10, 0);
+        // sorted top to bottom, then left to right
+        let found: Vec<_> = regions
+            .iter()
+            .map(|range| (range.min.x, range.min.y, range.max.x, range.max.y))
+            .collect();
+        assert_eq!(found, [(14, 0, 14, 0), (1, 1, 3, 3), (10, 8, 11, 8)]);
+
+        assert_eq!(find_regions(&pixels, 16, 10, 8).len(), 1);
+        assert!(find_regions(&[], 16, 10, 8).is_empty());
+    }
+}
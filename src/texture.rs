@@ -1,5 +1,6 @@
 use std::{
     cmp::Ordering,
+    collections::BTreeMap,
     path::PathBuf,
     sync::mpsc::{self, Receiver, Sender},
     thread,
@@ -10,19 +11,41 @@ use eframe::{
     epi,
 };
 use image::{imageops, DynamicImage, GenericImageView};
+use rayon::prelude::*;
 
 use crate::app::{PREVEW_IMAGE_LIMIT, PREVIEW_IMAGE_HEIGHT, PREVIEW_IMAGE_WIDTH};
+use crate::error::CropError;
+
+/// How many files are decoded at once for each thread, the next ones wait until they're all done.
+/// Keeps the full size images in memory at any one time down to a handful.
+const DECODES_PER_THREAD: usize = 2;
+
+/// One file from the loading threads.
+pub struct Loaded {
+    /// Index of the file in the paths being loaded, files are decoded in parallel so they come back in any order.
+    pub index: usize,
+    pub path: PathBuf,
+    /// The preview if the file is one of the ones shown, `None` past the preview limit.
+    /// An error if it couldn't be opened or decoded.
+    pub preview: Result<Option<DynamicImage>, CropError>,
+}
 
 #[derive(Default)]
-/// Texture manager holds onto the images and image paths, and is also responsible for their loading.
+/// Texture manager holds onto the image paths and their previews, and is also responsible for loading them.
+/// The full images aren't kept, cropping opens them again from the paths as it needs them.
 pub struct TextureManager {
-    /// Textures used by egui.
+    /// Textures used by egui, the nth is the preview of the nth input path.
     pub textures: Vec<Texture>,
-    /// The cached image files, loaded in by the file selector.
-    pub images: Vec<DynamicImage>,
-    /// The paths of each cached image file. Not gauranteed to be valid after loading.
-    /// Should update alongside `.images`.
+    /// The paths of each selected image file that opened, these are what get cropped.
     pub input_paths: Vec<PathBuf>,
+    /// Files that couldn't be opened and why, left out of `input_paths` until a retry opens them.
+    pub failed: Vec<(PathBuf, CropError)>,
+    /// How many files the loading threads have gotten through, and how many they were given.
+    /// Files are added in the order they were given, so this is also the index of the next one to add.
+    pub loaded: usize,
+    pub to_load: usize,
+    /// Files that came back before one given ahead of them, by index, waiting their turn.
+    pending: BTreeMap<usize, Loaded>,
 }
 
 impl TextureManager {
@@ -30,20 +53,79 @@ impl TextureManager {
     pub fn reload_textures(
         &mut self,
         alloc: &mut dyn epi::TextureAllocator,
-        sender: Sender<DynamicImage>,
+        sender: Sender<Loaded>,
     ) {
         // make sure to remove the textures first
         for tex in &self.textures {
             alloc.free(tex.id);
         }
         self.textures = vec![];
-        self.images = vec![];
+        self.failed = vec![];
 
-        // load the textures on a seperate thread because blocking
         let paths = self.input_paths.to_owned();
+        self.load(paths, PREVEW_IMAGE_LIMIT + 1, sender);
+    }
+
+    /// Tries opening a file that failed again, it goes after the other inputs if it opens this time.
+    pub fn retry_failed(&mut self, index: usize, sender: Sender<Loaded>) {
+        if index >= self.failed.len() {
+            return;
+        }
+        let (path, _) = self.failed.remove(index);
+        // only previewed if every input before it is too, so the previews keep lining up with the paths
+        let previews = if self.textures.len() == self.input_paths.len()
+            && self.textures.len() <= PREVEW_IMAGE_LIMIT
+        {
+            1
+        } else {
+            0
+        };
+        self.load(vec![path], previews, sender);
+    }
+
+    /// Forgets a file that couldn't be opened.
+    pub fn remove_failed(&mut self, index: usize) {
+        if index < self.failed.len() {
+            self.failed.remove(index);
+        }
+    }
+
+    /// Opens `paths` on a seperate thread because blocking, every file is sent back whether it opened or not.
+    /// Every file is decoded whole, so a file with a good header and a broken body is listed as failed
+    /// now instead of failing the crop, but only the first `previews` keep a preview, the rest are dropped.
+    ///
+    /// Files are decoded on every core, a few at a time each, see [`DECODES_PER_THREAD`].
+    fn load(&mut self, paths: Vec<PathBuf>, previews: usize, sender: Sender<Loaded>) {
+        self.loaded = 0;
+        self.to_load = paths.len();
+        self.pending = BTreeMap::new();
         thread::spawn(move || {
-            for path in paths.to_owned() {
-                sender.send(image::open(path).unwrap()).unwrap();
+            let batch = rayon::current_num_threads() * DECODES_PER_THREAD;
+            for (n, chunk) in paths.chunks(batch).enumerate() {
+                let sent = chunk.par_iter().enumerate().try_for_each_with(
+                    sender.clone(),
+                    |sender, (i, path)| {
+                        let index = n * batch + i;
+                        let preview = image::open(path)
+                            .map(|image| {
+                                if index < previews {
+                                    Some(TextureManager::preview_resize(&image))
+                                } else {
+                                    None
+                                }
+                            })
+                            .map_err(|e| CropError::decode(path, e));
+                        sender.send(Loaded {
+                            index,
+                            path: path.clone(),
+                            preview,
+                        })
+                    },
+                );
+                // the ui went away or started loading something else
+                if sent.is_err() {
+                    break;
+                }
             }
         });
     }
@@ -53,18 +135,18 @@ impl TextureManager {
     pub fn update_textures(
         &mut self,
         alloc: &mut dyn epi::TextureAllocator,
-        rx: &mut Receiver<DynamicImage>,
+        rx: &mut Receiver<Loaded>,
     ) -> bool {
         // loads 3 images of backup at once per frame, or if theres none it continues the frame as normal
-        for (num, image) in rx.try_iter().enumerate() {
-            self.load_texture(alloc, image);
+        for (num, loaded) in rx.try_iter().enumerate() {
+            self.add_loaded(alloc, loaded);
             if num == 1 {
                 break;
             }
         }
         match rx.try_recv() {
-            Ok(image) => {
-                self.load_texture(alloc, image);
+            Ok(loaded) => {
+                self.add_loaded(alloc, loaded);
 
                 true
             }
@@ -73,14 +155,50 @@ impl TextureManager {
         }
     }
 
+    /// Puts a file from the loading threads in by its index, holding onto it until every file before it is in.
+    fn add_loaded(&mut self, alloc: &mut dyn epi::TextureAllocator, loaded: Loaded) {
+        self.pending.insert(loaded.index, loaded);
+        while let Some(loaded) = self.pending.remove(&self.loaded) {
+            self.loaded += 1;
+            self.pair_loaded(alloc, loaded);
+        }
+    }
+
+    /// Pairs a file up with its path, in the order they were given.
+    /// A file that failed is taken out of the inputs, so the inputs and previews never get out of step.
+    fn pair_loaded(&mut self, alloc: &mut dyn epi::TextureAllocator, loaded: Loaded) {
+        let Loaded { path, preview, .. } = loaded;
+        let index = self.input_paths.iter().position(|input| *input == path);
+        match preview {
+            Ok(preview) => {
+                // a retried file isn't an input yet
+                let index = index.unwrap_or_else(|| {
+                    self.input_paths.push(path);
+                    self.input_paths.len() - 1
+                });
+                if let Some(preview) = preview {
+                    if index == self.textures.len() {
+                        self.load_texture(alloc, preview);
+                    }
+                }
+            }
+            Err(e) => {
+                if let Some(index) = index {
+                    self.input_paths.remove(index);
+                }
+                self.failed.push((path, e));
+            }
+        }
+    }
+
     /// Load an individual texture into memory. used by update_textures for each individual texture.
-    /// This is where resizing happens
+    /// The image is already resized down to a preview by the loading thread.
     fn load_texture(&mut self, alloc: &mut dyn epi::TextureAllocator, image: DynamicImage) {
         let (width, height);
         // hard limit of previews for ram
         if self.textures.len() <= PREVEW_IMAGE_LIMIT {
             let id = {
-                let img = TextureManager::preview_resize(&image).into_rgba8();
+                let img = image.into_rgba8();
                 width = img.width() as usize;
                 height = img.height() as usize;
                 if width > 300 || height > 250 {
@@ -97,7 +215,6 @@ impl TextureManager {
             let tex = Texture::new(id, width, height);
             self.textures.push(tex);
         }
-        self.images.push(image);
     }
 
     /// Creates new image from the first, resized at preview maxes
@@ -0,0 +1,182 @@
+//! Fixed size tile grid used by [`CropType::Tiles`](crate::CropType::Tiles).
+
+use image::{GenericImage, GenericImageView, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::crop::{kept, Pos2};
+
+/// One kept tile, and where it was put in a packed output.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tile {
+    /// Left edge of the tile on the original canvas.
+    pub x: u32,
+    /// Top edge of the tile on the original canvas.
+    pub y: u32,
+    /// Left edge of the tile in the packed output.
+    pub packed_x: u32,
+    /// Top edge of the tile in the packed output.
+    pub packed_y: u32,
+    /// Tiles on the right and bottom edges can be smaller than the tile size.
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Returns the top left corner of every tile that holds at least one of the different pixels,
+/// sorted top to bottom then left to right.
+pub fn find_tiles(pixels: &[Pos2], width: u32, height: u32, tile_size: u32) -> Vec<Pos2> {
+    let columns = width.div_ceil(tile_size);
+    let rows = height.div_ceil(tile_size);
+
+    let mut kept = vec![false; columns as usize * rows as usize];
+    for Pos2 { x, y } in pixels {
+        kept[(y / tile_size * columns + x / tile_size) as usize] = true;
+    }
+
+    kept.iter()
+        .enumerate()
+        .filter(|(_, kept)| **kept)
+        .map(|(i, _)| {
+            let i = i as u32;
+            Pos2::new(i % columns * tile_size, i / columns * tile_size)
+        })
+        .collect()
+}
+
+/// Copies the whole of every tile into a full size image, everything else left transparent.
+pub fn sparse(image: &RgbaImage, tiles: &[Pos2], tile_size: u32) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let mut new = RgbaImage::new(width, height);
+    for corner in tiles {
+        let (w, h) = tile_dimensions(*corner, width, height, tile_size);
+        for y in corner.y..corner.y + h {
+            for x in corner.x..corner.x + w {
+                // clear pixels in a tile are kept too, unlike the space around the tiles
+                new.put_pixel(x, y, kept(*image.get_pixel(x, y)));
+            }
+        }
+    }
+    new
+}
+
+/// Packs the tiles into a roughly square grid, returning the packed image and the index of where each tile went.
+pub fn pack(image: &RgbaImage, tiles: &[Pos2], tile_size: u32) -> (RgbaImage, Vec<Tile>) {
+    let (width, height) = image.dimensions();
+    let columns = (tiles.len() as f64).sqrt().ceil() as u32;
+    let rows = (tiles.len() as u32).div_ceil(columns);
+
+    let mut packed = RgbaImage::new(columns * tile_size, rows * tile_size);
+    let mut index = vec![];
+    for (i, corner) in tiles.iter().enumerate() {
+        let (w, h) = tile_dimensions(*corner, width, height, tile_size);
+        let packed_x = i as u32 % columns * tile_size;
+        let packed_y = i as u32 / columns * tile_size;
+        // can't fail, the tile is always inside both images
+        let _ = packed.copy_from(&image.view(corner.x, corner.y, w, h), packed_x, packed_y);
+
+        index.push(Tile {
+            x: corner.x,
+            y: corner.y,
+            packed_x,
+            packed_y,
+            width: w,
+            height: h,
+        });
+    }
+
+    (packed, index)
+}
+
+/// Size of the tile at `corner`, cut short by the edges of the canvas.
+fn tile_dimensions(corner: Pos2, width: u32, height: u32, tile_size: u32) -> (u32, u32) {
+    (
+        tile_size.min(width - corner.x),
+        tile_size.min(height - corner.y),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// Every pixel a different color, so it's clear where each one was copied from.
+    fn numbered(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| Rgba([x as u8, y as u8, 0, 255]))
+    }
+
+    #[test]
+    fn finds_the_tiles_holding_pixels() {
+        let pixels = [
+            Pos2::new(9, 9),
+            Pos2::new(0, 0),
+            Pos2::new(3, 3),
+            Pos2::new(5, 1),
+        ];
+        // 10 x 10 in tiles of 4, the last row and column are 2 wide
+        assert_eq!(
+            find_tiles(&pixels, 10, 10, 4),
+            [Pos2::new(0, 0), Pos2::new(4, 0), Pos2::new(8, 8)]
+        );
+    }
+
+    #[test]
+    fn packs_tiles_into_a_square_grid() {
+        let image = numbered(10, 10);
+        let tiles = [
+            Pos2::new(0, 0),
+            Pos2::new(4, 0),
+            Pos2::new(8, 0),
+            Pos2::new(0, 4),
+            Pos2::new(8, 8),
+        ];
+        let (packed, index) = pack(&image, &tiles, 4);
+
+        // 5 tiles go 3 across and 2 down
+        assert_eq!(packed.dimensions(), (12, 8));
+        let placed: Vec<(u32, u32, u32, u32)> = index
+            .iter()
+            .map(|tile| (tile.packed_x, tile.packed_y, tile.width, tile.height))
+            .collect();
+        assert_eq!(
+            placed,
+            [
+                (0, 0, 4, 4),
+                (4, 0, 4, 4),
+                (8, 0, 2, 4),
+                (0, 4, 4, 4),
+                (4, 4, 2, 2)
+            ]
+        );
+
+        for tile in &index {
+            for y in 0..tile.height {
+                for x in 0..tile.width {
+                    assert_eq!(
+                        packed.get_pixel(tile.packed_x + x, tile.packed_y + y),
+                        image.get_pixel(tile.x + x, tile.y + y)
+                    );
+                }
+            }
+        }
+        // what the edge tiles don't fill stays clear
+        assert_eq!(packed.get_pixel(11, 0)[3], 0);
+        assert_eq!(packed.get_pixel(7, 7)[3], 0);
+    }
+
+    #[test]
+    fn sparse_keeps_only_the_tiles() {
+        let image = numbered(10, 10);
+        let tiles = [Pos2::new(4, 0), Pos2::new(8, 8)];
+        let sparse = sparse(&image, &tiles, 4);
+
+        assert_eq!(sparse.dimensions(), image.dimensions());
+        for (x, y, px) in sparse.enumerate_pixels() {
+            let in_tile = (4..8).contains(&x) && y < 4 || x >= 8 && y >= 8;
+            if in_tile {
+                assert_eq!(px, image.get_pixel(x, y));
+            } else {
+                assert_eq!(px[3], 0, "{}, {}", x, y);
+            }
+        }
+    }
+}
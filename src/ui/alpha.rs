@@ -0,0 +1,24 @@
+use eframe::egui;
+
+use crate::config::{AlphaMode, Config};
+
+/// Draws the selector for how transparency is compared, lined up under the leniency slider.
+pub fn draw_alpha_selector(ui: &mut egui::Ui, config: &mut Config) {
+    ui.horizontal(|ui| {
+        ui.add_space(28.0);
+        egui::ComboBox::from_label("Alpha")
+            .selected_text(config.alpha_mode.name())
+            .show_ui(ui, |ui| {
+                for alpha_mode in [
+                    AlphaMode::Ignore,
+                    AlphaMode::Straight,
+                    AlphaMode::Premultiplied,
+                ]
+                .iter()
+                {
+                    ui.selectable_value(&mut config.alpha_mode, *alpha_mode, alpha_mode.name())
+                        .on_hover_text(alpha_mode.tooltip());
+                }
+            });
+    });
+}
@@ -0,0 +1,83 @@
+use eframe::egui;
+
+use crate::config::{BackgroundType, Config};
+
+/// Draws the sequential toggle, then the background type selector
+/// how many candidates to try when picking the best one and how far to search when aligning.
+pub fn draw_background_selector(ui: &mut egui::Ui, config: &mut Config) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut config.sequential, "chain images")
+            .on_hover_text("Crop every image against the one before it\ninstead of the background. Much smaller\nfor animation. The first image starts the chain.");
+        if config.sequential {
+            ui.add_space(10.0);
+            ui.add(
+                egui::DragValue::new(&mut config.keyframe_ratio)
+                    .clamp_range(0.0..=1.0)
+                    .speed(0.01)
+                    .fixed_decimals(2),
+            )
+            .on_hover_text("An image with more than this part of it\nchanged is kept whole, and the chain\nstarts over from it.");
+            ui.label("keyframe at");
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut config.cluster, "group similar images")
+            .on_hover_text("Split the images into groups that look alike,\neach cropped against its own background\ninto its own subfolder. For mixed sets,\nlike different outfits or camera angles.");
+        if config.cluster {
+            ui.add_space(10.0);
+            ui.add(
+                egui::DragValue::new(&mut config.cluster_ratio)
+                    .clamp_range(0.0..=1.0)
+                    .speed(0.01)
+                    .fixed_decimals(2),
+            )
+            .on_hover_text("An image with at least this part of it\ndifferent from every group starts its own.");
+            ui.label("new group at");
+        }
+    });
+
+    // the chain always starts from the first image
+    if config.sequential {
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_label("Background")
+            .selected_text(config.background_type.name())
+            .show_ui(ui, |ui| {
+                for background_type in [
+                    BackgroundType::Selected,
+                    BackgroundType::Best,
+                    BackgroundType::Median,
+                    BackgroundType::Mode,
+                ]
+                .iter()
+                {
+                    ui.selectable_value(
+                        &mut config.background_type,
+                        *background_type,
+                        background_type.name(),
+                    )
+                    .on_hover_text(background_type.tooltip());
+                }
+            });
+
+        if config.background_type == BackgroundType::Best {
+            ui.add_space(10.0);
+            ui.add(egui::DragValue::new(&mut config.background_samples).clamp_range(0.0..=10000.0))
+                .on_hover_text("How many images to try, spread out evenly.\n0 tries every image.");
+            ui.label("tries");
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::DragValue::new(&mut config.align)
+                .clamp_range(0.0..=64.0)
+                .suffix(" px"),
+        )
+        .on_hover_text("Search up to this many pixels each way for the\nshift that lines each image up with the background,\nfor sprites that moved a little between exports.\n0 doesn't align.");
+        ui.label("align");
+    });
+}
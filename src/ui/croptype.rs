@@ -2,7 +2,8 @@ use eframe::egui;
 
 use crate::config::{Config, CropType};
 
-/// Draws the croptype selector and the "resize output" to the right of it.
+/// Draws the croptype selector and the "resize output" to the right of it,
+/// plus the per image range or tile size underneath, depending on the crop type.
 pub fn draw_croptype_selector(ui: &mut egui::Ui, config: &mut Config) {
     // centering is stupid, just stop thinking about it
     ui.horizontal(|ui| {
@@ -22,9 +23,56 @@ pub fn draw_croptype_selector(ui: &mut egui::Ui, config: &mut Config) {
                     CropType::Exact.name(),
                 )
                 .on_hover_text(CropType::Exact.tooltip());
+                ui.selectable_value(
+                    &mut config.crop_type,
+                    CropType::Regions,
+                    CropType::Regions.name(),
+                )
+                .on_hover_text(CropType::Regions.tooltip());
+                ui.selectable_value(
+                    &mut config.crop_type,
+                    CropType::Tiles,
+                    CropType::Tiles.name(),
+                )
+                .on_hover_text(CropType::Tiles.tooltip());
             });
         ui.add_space(ui.available_width() - 118.0);
-        ui.checkbox(&mut config.resize_output, "resize output")
-            .on_hover_text("When false, cropped out space\nis replaced with empty pixels.");
+        // regions are always cut out tight, so they get the merge distance instead of resizing
+        if config.crop_type == CropType::Regions {
+            ui.add(
+                egui::DragValue::new(&mut config.merge_distance)
+                    .clamp_range(0.0..=512.0)
+                    .suffix(" px"),
+            )
+            .on_hover_text("Regions closer than this many\npixels are merged into one.");
+            ui.label("merge");
+        } else if config.crop_type == CropType::Tiles {
+            ui.checkbox(&mut config.resize_output, "pack tiles")
+                .on_hover_text("When false, tiles are kept in place\nand the rest is empty pixels.\nWhen true, only the tiles are saved\npacked together, with their positions\nin the manifest.");
+        } else {
+            ui.checkbox(&mut config.resize_output, "resize output")
+                .on_hover_text("When false, cropped out space\nis replaced with empty pixels.");
+        }
     });
+
+    if matches!(config.crop_type, CropType::Rectangle | CropType::Exact) {
+        ui.horizontal(|ui| {
+            ui.add_space(28.0);
+            ui.checkbox(&mut config.per_image_range, "range per image")
+                .on_hover_text("Cut every image to its own changed area,\ninstead of the area that changed in any image.\nSmaller outputs when only a few images\nchange a lot.");
+        });
+    }
+
+    if config.crop_type == CropType::Tiles {
+        ui.horizontal(|ui| {
+            ui.add_space(28.0);
+            ui.add(
+                egui::DragValue::new(&mut config.tile_size)
+                    .clamp_range(1.0..=1024.0)
+                    .suffix(" px"),
+            )
+            .on_hover_text("Width and height of each tile.\n8 lines up with jpeg blocks,\n4 with most gpu texture blocks.");
+            ui.label("tile size");
+        });
+    }
 }
@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+
+use eframe::egui;
+
+use crate::error::CropError;
+
+/// What to do with a file that couldn't be opened, by its index in the failed list.
+pub enum FailedAction {
+    Retry(usize),
+    Remove(usize),
+}
+
+/// Draws the files that couldn't be opened, each with why and buttons to retry or remove it.
+/// Nothing is drawn if every file opened.
+pub fn draw_failed_files(
+    ui: &mut egui::Ui,
+    failed: &[(PathBuf, CropError)],
+) -> Option<FailedAction> {
+    if failed.is_empty() {
+        return None;
+    }
+
+    let mut action = None;
+    egui::CollapsingHeader::new(format!("{} files couldn't be opened", failed.len()))
+        .default_open(true)
+        .show(ui, |ui| {
+            ui.label("These are left out of the crop.");
+            for (i, (path, e)) in failed.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.button("retry").clicked() {
+                        action = Some(FailedAction::Retry(i));
+                    }
+                    if ui.button("remove").clicked() {
+                        action = Some(FailedAction::Remove(i));
+                    }
+                    let name = path
+                        .file_name()
+                        .unwrap_or_else(|| path.as_os_str())
+                        .to_string_lossy()
+                        .into_owned();
+                    // the path is already in the name, so just what went wrong with it
+                    let reason = match e {
+                        CropError::Decode { source, .. } => source.to_string(),
+                        e => e.to_string(),
+                    };
+                    ui.label(format!("{}: {}", name, reason))
+                        .on_hover_text(path.display().to_string());
+                });
+            }
+        });
+    action
+}
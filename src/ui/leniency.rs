@@ -1,8 +1,53 @@
 use eframe::egui;
 
-use crate::config::Config;
+use crate::config::{Config, MetricType};
 
+/// Draws the metric selector, then the leniency slider in the metric's units,
+/// or a threshold for every channel for [`MetricType::PerChannel`].
 pub fn draw_leniency_slider(ui: &mut egui::Ui, config: &mut Config) {
+    ui.horizontal(|ui| {
+        ui.add_space(28.0);
+        egui::ComboBox::from_label("Metric")
+            .selected_text(config.metric.name())
+            .show_ui(ui, |ui| {
+                for metric in [
+                    MetricType::Rgb,
+                    MetricType::LinearRgb,
+                    MetricType::MaxChannel,
+                    MetricType::PerChannel,
+                    MetricType::DeltaE76,
+                    MetricType::DeltaE2000,
+                ]
+                .iter()
+                {
+                    ui.selectable_value(&mut config.metric, *metric, metric.name())
+                        .on_hover_text(metric.tooltip());
+                }
+            });
+    });
+
+    if config.metric == MetricType::PerChannel {
+        ui.horizontal(|ui| {
+            ui.add_space(28.0);
+            let channels = ["R", "G", "B", "A"].iter();
+            for (threshold, channel) in config.channel_thresholds.iter_mut().zip(channels) {
+                ui.add(
+                    egui::DragValue::new(threshold)
+                        .clamp_range(0.0..=255.0)
+                        .prefix(format!("{} ", channel)),
+                )
+                .on_hover_text(
+                    "How many levels the channel can change\nbefore the pixel counts as different.",
+                );
+            }
+        });
+        return;
+    }
+
+    // switching metrics can leave the leniency past what the new one goes up to
+    let max = config.metric.max_leniency();
+    config.leniency = config.leniency.min(max);
+
     // leniency slider "centered" through the use "on the fly" slider styling and horizontal spacing
     // it is extra padded this way so that the value text box used on the slider doesn't expand the width of the program when clicked
     // egui is pain
@@ -11,8 +56,8 @@ pub fn draw_leniency_slider(ui: &mut egui::Ui, config: &mut Config) {
         ui.style_mut().spacing.slider_width = ui.available_width()-100.0- spacing*2.0;
         ui.horizontal(|ui| {
             ui.add_space(spacing);
-            ui.add(egui::Slider::new(&mut config.leniency, 0.0..=99.9)
-                .text("leniency")
+            ui.add(egui::Slider::new(&mut config.leniency, 0.0..=max)
+                .text(format!("leniency ({})", config.metric.unit()))
                 .clamp_to_range(true)
                 .fixed_decimals(1)
             ).on_hover_text("None means any difference will be saved.\nLossless formats should probably be 0, lossy should be kept very low.");
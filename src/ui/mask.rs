@@ -0,0 +1,46 @@
+use eframe::egui;
+
+use crate::config::Config;
+
+/// Draws the mask cleanup options in a row under the leniency slider, 0 turns each one off.
+pub fn draw_mask_cleanup(ui: &mut egui::Ui, config: &mut Config) {
+    ui.horizontal(|ui| {
+        ui.add_space(28.0);
+        ui.add(
+            egui::DragValue::new(&mut config.smooth)
+                .clamp_range(0.0..=16.0)
+                .speed(0.1)
+                .fixed_decimals(1)
+                .suffix(" px"),
+        )
+        .on_hover_text("Blurs the images before comparing,\nso jpeg and noise don't count as changes.\nOutputs are still copied unblurred.");
+        ui.label("smooth");
+
+        ui.add_space(10.0);
+        ui.add(
+            egui::DragValue::new(&mut config.min_region_size)
+                .clamp_range(0.0..=4096.0)
+                .suffix(" px"),
+        )
+        .on_hover_text("Changed pixels in groups smaller than\nthis are dropped as noise.");
+        ui.label("min size");
+
+        ui.add_space(10.0);
+        ui.add(
+            egui::DragValue::new(&mut config.close)
+                .clamp_range(0.0..=64.0)
+                .suffix(" px"),
+        )
+        .on_hover_text("Fills holes in the changed pixels\nup to this many pixels wide.");
+        ui.label("fill holes");
+
+        ui.add_space(10.0);
+        ui.add(
+            egui::DragValue::new(&mut config.dilate)
+                .clamp_range(0.0..=64.0)
+                .suffix(" px"),
+        )
+        .on_hover_text("Grows the changed pixels outwards,\nso lossy edges aren't left behind.");
+        ui.label("grow");
+    });
+}
@@ -4,10 +4,17 @@ use eframe::egui::{self, Align, Label, Response};
 
 use crate::{config::Config, texture::TextureManager};
 
+pub(crate) mod alpha;
+pub(crate) mod background;
 pub(crate) mod croptype;
+pub(crate) mod failed;
 pub(crate) mod filename;
 pub(crate) mod leniency;
+pub(crate) mod mask;
+pub(crate) mod pad;
 pub(crate) mod previewer;
+pub(crate) mod progress;
+pub(crate) mod reconstruct;
 
 /// Draws a header on the left, and a browse button for something on the right.
 /// Returns the response of the browse button.
@@ -27,6 +34,8 @@ pub fn crop_button(
 ) -> Response {
     // disabled until conditions are met
     let crop_enabled = !(tex_manager.input_paths.len() == 0
+        || (!config.background_type.is_synthetic()
+            && config.background >= tex_manager.input_paths.len())
         || config.output_path.as_os_str().is_empty()
         || config.bg_name.is_illegal()
         || config.file_name.is_illegal());
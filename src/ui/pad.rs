@@ -0,0 +1,33 @@
+use eframe::egui;
+
+use crate::config::{Anchor, Config};
+
+/// Draws the toggle for padding images of different sizes, and where smaller ones go on the canvas.
+pub fn draw_pad_selector(ui: &mut egui::Ui, config: &mut Config) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut config.pad, "pad different sizes")
+            .on_hover_text("Put smaller images on a transparent canvas\nthe size of the biggest one instead of refusing\nthem. Rebuilt images get their own size back.\nSet this before opening the images.");
+        if config.pad {
+            ui.add_space(10.0);
+            egui::ComboBox::from_label("Anchor")
+                .selected_text(config.anchor.name())
+                .show_ui(ui, |ui| {
+                    for anchor in [
+                        Anchor::TopLeft,
+                        Anchor::Top,
+                        Anchor::TopRight,
+                        Anchor::Left,
+                        Anchor::Center,
+                        Anchor::Right,
+                        Anchor::BottomLeft,
+                        Anchor::Bottom,
+                        Anchor::BottomRight,
+                    ]
+                    .iter()
+                    {
+                        ui.selectable_value(&mut config.anchor, *anchor, anchor.name());
+                    }
+                });
+        }
+    });
+}
@@ -1,12 +1,25 @@
-use eframe::egui::{self, Align, NumExt};
+use eframe::egui::{self, Align, NumExt, Response};
 
 use crate::{
     app::{DEFAULT_PREVIEW_HEIGHT, LOWER_PANEL_HEIGHT, PREVIEW_IMAGE_HEIGHT},
-    texture::TextureManager,
+    config::{BackgroundType, Config},
+    texture::{Texture, TextureManager},
 };
 
 /// draws the image previewer that shows the before-cropped images.
-pub fn draw_file_previewer(ui: &mut egui::Ui, tex_manager: &mut TextureManager) {
+/// Clicking an image makes it the background.
+pub fn draw_file_previewer(
+    ui: &mut egui::Ui,
+    tex_manager: &mut TextureManager,
+    config: &mut Config,
+) {
+    // a made up background isn't any of the images, so nothing is highlighted
+    let background = if config.background_type.is_synthetic() {
+        None
+    } else {
+        Some(config.background)
+    };
+
     if tex_manager.textures.len() == 0 {
         // small placeholder text until images are avilable
         ui.vertical_centered(|ui| {
@@ -38,7 +51,7 @@ pub fn draw_file_previewer(ui: &mut egui::Ui, tex_manager: &mut TextureManager)
                     .spacing(egui::Vec2::new(5.0, 5.0))
                     .show(ui, |ui| {
                         let mut counter = 0;
-                        for tex in &tex_manager.textures {
+                        for (i, tex) in tex_manager.textures.iter().enumerate() {
                             if counter == columns {
                                 counter = 0;
                                 ui.end_row();
@@ -48,33 +61,23 @@ pub fn draw_file_previewer(ui: &mut egui::Ui, tex_manager: &mut TextureManager)
                             // stuff to make sure it fits nice n snug but isnt too tall or wide
                             let ratio: f32 = tex.height as f32 / tex.width as f32;
                             let predicted_height = ui.available_width() * ratio;
-                            if predicted_height > PREVIEW_IMAGE_HEIGHT {
-                                ui.vertical_centered(|ui| {
-                                    ui.image(
-                                        tex.id,
-                                        egui::Vec2::new(
-                                            PREVIEW_IMAGE_HEIGHT / ratio,
-                                            PREVIEW_IMAGE_HEIGHT,
-                                        ),
-                                    );
-                                });
+                            let size = if predicted_height > PREVIEW_IMAGE_HEIGHT {
+                                egui::Vec2::new(PREVIEW_IMAGE_HEIGHT / ratio, PREVIEW_IMAGE_HEIGHT)
+                            // fit to available space only if the width is larger than the available space, otherwise just do normal texture width
+                            } else if ui.available_width() < tex.width as f32 {
+                                egui::Vec2::new(ui.available_width(), predicted_height)
                             } else {
-                                // fit to available space only if the width is larger than the available space, otherwise just do normal texture width
-                                if ui.available_width() < tex.width as f32 {
-                                    ui.vertical_centered(|ui| {
-                                        ui.image(
-                                            tex.id,
-                                            egui::Vec2::new(ui.available_width(), predicted_height),
-                                        );
-                                    });
-                                } else {
-                                    ui.vertical_centered(|ui| {
-                                        ui.image(
-                                            tex.id,
-                                            egui::Vec2::new(tex.width as f32, tex.height as f32),
-                                        );
-                                    });
-                                }
+                                egui::Vec2::new(tex.width as f32, tex.height as f32)
+                            };
+
+                            let response = ui
+                                .vertical_centered(|ui| {
+                                    preview_image(ui, tex, size, background == Some(i))
+                                })
+                                .inner;
+                            if response.clicked() {
+                                config.background = i;
+                                config.background_type = BackgroundType::Selected;
                             }
                         }
                     });
@@ -85,3 +88,23 @@ pub fn draw_file_previewer(ui: &mut egui::Ui, tex_manager: &mut TextureManager)
             });
     }
 }
+
+/// Draws a single clickable preview, highlighted if it's the background.
+fn preview_image(
+    ui: &mut egui::Ui,
+    tex: &Texture,
+    size: egui::Vec2,
+    is_background: bool,
+) -> Response {
+    let response = ui.add(
+        egui::ImageButton::new(tex.id, size)
+            .frame(false)
+            .selected(is_background),
+    );
+
+    if is_background {
+        response.on_hover_text("Background image")
+    } else {
+        response.on_hover_text("Click to use as the background")
+    }
+}
@@ -0,0 +1,44 @@
+use eframe::egui::{self, Response};
+
+use crate::progress::Update;
+
+/// Height of the progress bar.
+const BAR_HEIGHT: f32 = 18.0;
+
+/// Draws how far along a running crop is, a bar for the current phase and a cancel button.
+/// `update` is the latest progress, `None` before the first one comes in.
+/// Returns the response of the cancel button.
+pub fn draw_crop_progress(ui: &mut egui::Ui, update: Option<&Update>) -> Response {
+    let (text, fraction) = match update {
+        Some(update) => (
+            format!(
+                "{} {}/{} images, {:.1} MB written",
+                update.phase.name(),
+                update.done,
+                update.total,
+                update.bytes_written as f64 / 1_000_000.0
+            ),
+            update.done as f32 / update.total.max(1) as f32,
+        ),
+        None => ("starting crop".to_string(), 0.0),
+    };
+
+    ui.label(text);
+    // painted by hand, a filled bar over an empty one
+    let (rect, _) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), BAR_HEIGHT),
+        egui::Sense::hover(),
+    );
+    ui.painter()
+        .rect_filled(rect, 4.0, ui.visuals().extreme_bg_color);
+    let filled =
+        egui::Rect::from_min_size(rect.min, egui::vec2(rect.width() * fraction, rect.height()));
+    ui.painter()
+        .rect_filled(filled, 4.0, ui.visuals().selection.bg_fill);
+
+    ui.vertical_centered_justified(|ui| ui.button("Cancel"))
+        .inner
+        .on_hover_text(
+            "Stops the crop at the next image.\nOutputs already saved are left where they are.",
+        )
+}
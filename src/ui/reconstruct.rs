@@ -0,0 +1,14 @@
+use eframe::egui::{self, Response};
+
+/// Draws the collapsible reconstruct section, for rebuilding originals out of a previous crop's outputs.
+/// Returns the response of the reconstruct button while the section is open.
+pub fn draw_reconstruct_section(ui: &mut egui::Ui) -> Option<Response> {
+    egui::CollapsingHeader::new("Reconstruct")
+        .show(ui, |ui| {
+            ui.label("Rebuilds the original images from the manifest.json a crop saved.");
+            ui.vertical_centered_justified(|ui| ui.button("Reconstruct"))
+                .inner
+                .on_hover_text("Pick a manifest, then where to put the rebuilt images.")
+        })
+        .body_returned
+}